@@ -121,9 +121,14 @@ impl<C: DecoderContext> Decoder<C> {
         Self { config }
     }
     #[inline]
-    fn write_font(&self, idx: usize, fid_data: &mut Bytes, mut out: C::Writer) -> C::Writer {
+    fn write_font(
+        &self,
+        idx: usize,
+        mut urange_list: Bytes,
+        fid_data: &mut Bytes,
+        mut out: C::Writer,
+    ) -> C::Writer {
         let init = &self.config;
-        let mut urange_list = init.urange_data();
         while !urange_list.is_empty() {
             let fid = fid_data.read_string();
 
@@ -167,8 +172,20 @@ impl<C: DecoderContext> Decoder<C> {
 
     pub fn decode(&self, mut out: C::Writer) -> C::Writer {
         let mut fid_data = self.config.fid_data();
+        let mut urange_data = self.config.urange_data();
+        // Either one partition shared by all faces, or one per face.
+        let per_face = match urange_data.read_varint() {
+            0 => false,
+            1 => true,
+            _ => crate::core::unreachable(),
+        };
         for idx in 0..self.config.font_face_count() {
-            out = self.write_font(idx, &mut fid_data, out);
+            let urange_list = if per_face {
+                Bytes(urange_data.read_string())
+            } else {
+                urange_data
+            };
+            out = self.write_font(idx, urange_list, &mut fid_data, out);
         }
         out
     }
@@ -1,9 +1,37 @@
 use anyhow::Result;
 use fontchan_unicode::URange;
 
+/// Encodes a partition shared by all the font faces.
 pub fn encode_urange_data<'a>(partition: impl Iterator<Item = &'a URange>) -> Result<Vec<u8>> {
     use integer_encoding::VarIntWriter;
     let mut out = vec![];
+    out.write_varint(0u32)?;
+    write_partition(&mut out, partition)?;
+    Ok(out)
+}
+
+/// Encodes a partition for each of the font faces, in the order of the faces.
+pub fn encode_face_urange_data<'a, P>(partitions: impl Iterator<Item = P>) -> Result<Vec<u8>>
+where
+    P: Iterator<Item = &'a URange>,
+{
+    use integer_encoding::VarIntWriter;
+    let mut out = vec![];
+    out.write_varint(1u32)?;
+    for partition in partitions {
+        let mut data = vec![];
+        write_partition(&mut data, partition)?;
+        out.write_varint(data.len() as u32)?;
+        out.extend_from_slice(&data);
+    }
+    Ok(out)
+}
+
+fn write_partition<'a>(
+    out: &mut Vec<u8>,
+    partition: impl Iterator<Item = &'a URange>,
+) -> Result<()> {
+    use integer_encoding::VarIntWriter;
     for urange in partition {
         let single_count = urange.single_count();
         out.write_varint(single_count as u32)?;
@@ -22,7 +50,7 @@ pub fn encode_urange_data<'a>(partition: impl Iterator<Item = &'a URange>) -> Re
             prev = range.end as u32;
         }
     }
-    Ok(out)
+    Ok(())
 }
 
 pub fn encode_fid_data<'a>(fids: impl Iterator<Item = &'a str>) -> Result<Vec<u8>> {
@@ -34,3 +62,54 @@ pub fn encode_fid_data<'a>(fids: impl Iterator<Item = &'a str>) -> Result<Vec<u8
     }
     Ok(out)
 }
+
+#[cfg(test)]
+fn decode_css(urange_data: &[u8], fid_data: &[u8], faces: &[&str]) -> String {
+    let out = crate::StdContext {
+        writer: crate::VecWriter::new(),
+        urange_data,
+        fid_data,
+        faces,
+        ext_getter: |face: &&str| face.as_bytes(),
+        src_getter: |_, fid| fid,
+    }
+    .decode();
+    String::from_utf8(out.into_vec()).unwrap()
+}
+
+#[test]
+fn test_roundtrip() {
+    use fontchan_unicode::URangeBuilder;
+
+    let ranges = ["ac", "xyz"].map(|s| URangeBuilder::from_chars(s.chars()).build());
+    let fids = encode_fid_data(["f1", "f2", "g1", "g2"].into_iter()).unwrap();
+
+    let shared = encode_urange_data(ranges.iter()).unwrap();
+    assert_eq!(
+        decode_css(&shared, &fids, &["A", "B"]),
+        "@font-face{Af1unicode-range:U+61,U+63;}\
+         @font-face{Af2unicode-range:U+78-7a;}\
+         @font-face{Bg1unicode-range:U+61,U+63;}\
+         @font-face{Bg2unicode-range:U+78-7a;}"
+    );
+
+    let fids = encode_fid_data(["f1", "h1", "h2"].into_iter()).unwrap();
+    let per_face = encode_face_urange_data(
+        [&ranges[..1], &[], &ranges[..]]
+            .map(|p| p.iter())
+            .into_iter(),
+    )
+    .unwrap();
+    assert_eq!(
+        decode_css(&per_face, &fids, &["A", "B", "C"]),
+        "@font-face{Af1unicode-range:U+61,U+63;}\
+         @font-face{Ch1unicode-range:U+61,U+63;}\
+         @font-face{Ch2unicode-range:U+78-7a;}"
+    );
+}
+
+#[test]
+#[should_panic(expected = "unreachable")]
+fn test_unknown_urange_layout() {
+    decode_css(&[2, 0], &[], &["A"]);
+}
@@ -6,37 +6,78 @@ use std::{
     usize,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use fontchan_unicode::{URange, URangeBuilder};
 use fontchan_util::{autobox, factory, Registry};
-use fontchan_util::{routine, Req};
+use fontchan_util::{routine, Req, RoutineArg};
 
 use crate::{
     char_base::CharBaseProvider,
     char_freq::CharFreqProvider,
+    css::{first_family, strip_comments},
+    manifest::Manifest,
     pages::{Page, PagesProvider},
     PartSize,
 };
 
-fn do_partition(mut iter: impl Iterator<Item = char>, len: usize, num: usize) -> Vec<URange> {
-    let n_chunks = len / num;
-    let residual = len % num;
-    let mut res = Vec::with_capacity(n_chunks);
-    for i in 0..n_chunks {
-        let mut chunk_size = num;
-        if i == n_chunks - 1 {
-            chunk_size += residual;
+fn chunk_sizes(part_size: PartSize, len: usize) -> Vec<usize> {
+    match part_size {
+        PartSize::Chars(num) => {
+            let num = num.max(1);
+            let (n_chunks, residual) = (len / num, len % num);
+            let mut sizes = vec![num; n_chunks];
+            if residual > 0 {
+                sizes.push(residual);
+            }
+            sizes
+        }
+        PartSize::Count(count) => {
+            let count = count.min(len);
+            if count == 0 {
+                return vec![];
+            }
+            let (base, residual) = (len / count, len % count);
+            (0..count).map(|i| base + (i < residual) as usize).collect()
+        }
+        PartSize::Tiered { first, growth, max } => {
+            let max = max.unwrap_or(usize::MAX);
+            let mut sizes = vec![];
+            let mut remaining = len;
+            let mut size = first.max(1) as f64;
+            while remaining > 0 {
+                let chunk_size = (size.round() as usize).min(max).max(1).min(remaining);
+                sizes.push(chunk_size);
+                remaining -= chunk_size;
+                size *= growth;
+            }
+            sizes
         }
+    }
+}
+
+pub(crate) fn chunk_size_limit(part_size: PartSize, len: usize) -> Option<usize> {
+    match part_size {
+        PartSize::Chars(num) => Some(num.max(1)),
+        PartSize::Count(count) => Some(len.div_ceil(count.max(1))),
+        PartSize::Tiered { max, .. } => max.map(|max| max.max(1)),
+    }
+}
+
+fn do_partition(mut iter: impl Iterator<Item = char>, sizes: Vec<usize>) -> Vec<URange> {
+    let mut res = Vec::with_capacity(sizes.len());
+    for chunk_size in sizes {
         let str = iter.by_ref().take(chunk_size).collect::<String>();
-        // println!("{i} => {str}");
         res.push(URangeBuilder::from_chars(str.chars()).build());
     }
     return res;
 }
 
-fn do_partition_exact(iter: impl ExactSizeIterator<Item = char>, num: usize) -> Vec<URange> {
-    let len = iter.len();
-    do_partition(iter, len, num)
+fn do_partition_exact(
+    iter: impl ExactSizeIterator<Item = char>,
+    part_size: PartSize,
+) -> Vec<URange> {
+    let sizes = chunk_sizes(part_size, iter.len());
+    do_partition(iter, sizes)
 }
 
 pub(crate) struct AlgorithmContext {
@@ -47,7 +88,21 @@ pub(crate) struct AlgorithmContext {
 }
 
 pub(crate) trait AlgorithmImpl {
-    fn partition(&self, config: &AlgorithmContext) -> Vec<URange>;
+    /// Partitions with `pages`, which are either those of `config.pages` or a subset of them.
+    fn partition(
+        &self,
+        config: &AlgorithmContext,
+        pages: Option<Cow<[Page]>>,
+    ) -> Result<Vec<URange>>;
+    /// Whether the produced chunks are sized according to `part_size`.
+    /// Imported partitions keep their own sizes.
+    fn respects_part_size(&self) -> bool {
+        true
+    }
+    /// Digest of the inputs that the imported partition was made from.
+    fn expected_digest(&self) -> Option<&str> {
+        None
+    }
 }
 autobox!(AlgorithmImpl);
 
@@ -60,14 +115,16 @@ impl SortByOccurrence {
         }
         Ok(Self)
     }
-    fn case_with_pages_only(num: usize, pages: Cow<[Page]>) -> Vec<URange> {
+    fn case_with_pages_only(part_size: PartSize, pages: Cow<[Page]>) -> Vec<URange> {
         let mut stats = HashMap::<char, isize>::new();
-        for &ch in pages.iter().flatten() {
-            *stats.entry(ch).or_insert(0) -= 1;
+        for page in pages.iter() {
+            for &ch in page {
+                *stats.entry(ch).or_insert(0) -= page.weight() as isize;
+            }
         }
         let mut chars: Vec<_> = stats.into_iter().map(|(c, f)| (f, c)).collect();
         chars.sort_unstable();
-        do_partition_exact(chars.into_iter().map(|(_, c)| c), num)
+        do_partition_exact(chars.into_iter().map(|(_, c)| c), part_size)
     }
     fn lift_ascii<T: Copy>(stats: &mut HashMap<char, T>, value: T) {
         for char in '\u{0}'..='\u{ff}' {
@@ -75,7 +132,7 @@ impl SortByOccurrence {
         }
     }
     fn case_with_pages_generic(
-        num: usize,
+        part_size: PartSize,
         pages: Cow<[Page]>,
         char_freq: Option<Cow<[char]>>,
         char_base: Option<Cow<HashSet<char>>>,
@@ -94,19 +151,25 @@ impl SortByOccurrence {
             .zip(std::iter::repeat(isize::MAX))
             .chain(freq_stats)
             .collect::<HashMap<_, _>>();
-        for ch in pages.iter().flatten() {
-            stats.entry(*ch).and_modify(|f| *f = (*f).min(0) - 1);
+        for page in pages.iter() {
+            for ch in page {
+                if char_base.is_some_and(|cb| !cb.contains(ch)) {
+                    continue;
+                }
+                let f = stats.entry(*ch).or_insert(0);
+                *f = (*f).min(0) - page.weight() as isize;
+            }
         }
         Self::lift_ascii(&mut stats, isize::MIN);
         let mut chars: Vec<_> = stats.into_iter().map(|(c, f)| (f, c)).collect();
         chars.sort_unstable();
-        do_partition_exact(chars.into_iter().map(|(_, c)| c), num)
+        do_partition_exact(chars.into_iter().map(|(_, c)| c), part_size)
     }
-    fn case_with_charfreq_only(num: usize, char_freq: Cow<[char]>) -> Vec<URange> {
-        do_partition_exact(char_freq.into_iter().cloned(), num)
+    fn case_with_charfreq_only(part_size: PartSize, char_freq: Cow<[char]>) -> Vec<URange> {
+        do_partition_exact(char_freq.into_iter().cloned(), part_size)
     }
     fn case_with_charfreq_charbase(
-        num: usize,
+        part_size: PartSize,
         char_freq: Cow<[char]>,
         char_base: Cow<HashSet<char>>,
     ) -> Vec<URange> {
@@ -120,37 +183,198 @@ impl SortByOccurrence {
         seq.sort_unstable();
         seq.dedup_by_key(|(c, _)| *c);
         seq.sort_unstable_by_key(|(c, i)| (*i, *c));
-        do_partition_exact(seq.into_iter().map(|(c, _)| c), num)
+        do_partition_exact(seq.into_iter().map(|(c, _)| c), part_size)
     }
-    fn case_with_charbase(num: usize, char_base: Cow<HashSet<char>>) -> Vec<URange> {
+    fn case_with_charbase(part_size: PartSize, char_base: Cow<HashSet<char>>) -> Vec<URange> {
         let mut seq: Vec<_> = char_base.iter().cloned().collect();
         seq.sort_unstable();
-        do_partition_exact(seq.into_iter(), num)
+        do_partition_exact(seq.into_iter(), part_size)
     }
 }
 
 impl AlgorithmImpl for SortByOccurrence {
-    fn partition(&self, config: &AlgorithmContext) -> Vec<URange> {
-        let num = match config.part_size {
-            PartSize::Chars(num) => num,
-        };
-        let pages = config.pages.as_ref().map(|p| p.pages());
-        let char_base = config.char_base.as_ref().map(|p| p.char_base());
+    fn partition(
+        &self,
+        config: &AlgorithmContext,
+        pages: Option<Cow<[Page]>>,
+    ) -> Result<Vec<URange>> {
+        let part_size = config.part_size;
+        let char_base = config
+            .char_base
+            .as_ref()
+            .map(|p| p.char_base())
+            .transpose()?;
         let char_freq = config.char_freq.as_ref().map(|p| p.char_freq());
-        match (pages, char_base, char_freq) {
-            (Some(pages), None, None) => SortByOccurrence::case_with_pages_only(num, pages),
+        let ranges = match (pages, char_base, char_freq) {
+            (Some(pages), None, None) => SortByOccurrence::case_with_pages_only(part_size, pages),
             (Some(pages), char_base, char_freq) => {
-                SortByOccurrence::case_with_pages_generic(num, pages, char_freq, char_base)
+                SortByOccurrence::case_with_pages_generic(part_size, pages, char_freq, char_base)
             }
             (None, None, Some(char_freq)) => {
-                SortByOccurrence::case_with_charfreq_only(num, char_freq)
+                SortByOccurrence::case_with_charfreq_only(part_size, char_freq)
             }
             (None, Some(char_base), Some(char_freq)) => {
-                SortByOccurrence::case_with_charfreq_charbase(num, char_freq, char_base)
+                SortByOccurrence::case_with_charfreq_charbase(part_size, char_freq, char_base)
+            }
+            (None, Some(char_base), None) => {
+                SortByOccurrence::case_with_charbase(part_size, char_base)
             }
-            (None, Some(char_base), None) => SortByOccurrence::case_with_charbase(num, char_base),
             _ => unreachable!(),
+        };
+        Ok(ranges)
+    }
+}
+
+/// Appends chunks, sized by `part_size`, of the chars left out of imported `ranges`,
+/// i.e., those of the char base or, without one, of `pages`.
+fn with_residual(
+    config: &AlgorithmContext,
+    pages: Option<Cow<[Page]>>,
+    mut ranges: Vec<URange>,
+) -> Result<Vec<URange>> {
+    let covered = ranges
+        .iter()
+        .flat_map(|range| range.as_chars())
+        .collect::<HashSet<_>>();
+    let chars = match config
+        .char_base
+        .as_ref()
+        .map(|p| p.char_base())
+        .transpose()?
+    {
+        Some(char_base) => char_base.iter().cloned().collect::<HashSet<_>>(),
+        None => pages
+            .iter()
+            .flat_map(|pages| pages.iter())
+            .flatten()
+            .cloned()
+            .collect(),
+    };
+    let mut residual = chars
+        .into_iter()
+        .filter(|ch| !covered.contains(ch))
+        .collect::<Vec<_>>();
+    residual.sort_unstable();
+    ranges.extend(do_partition_exact(residual.into_iter(), config.part_size));
+    Ok(ranges)
+}
+
+pub struct FromManifest {
+    ranges: Vec<URange>,
+    digest: String,
+}
+
+impl FromManifest {
+    fn new(path: &RoutineArg) -> Result<Self> {
+        let manifest = Manifest::load(path.required()?)?;
+        Ok(Self {
+            ranges: manifest.ranges,
+            digest: manifest.digest,
+        })
+    }
+}
+
+impl AlgorithmImpl for FromManifest {
+    fn partition(
+        &self,
+        config: &AlgorithmContext,
+        pages: Option<Cow<[Page]>>,
+    ) -> Result<Vec<URange>> {
+        with_residual(config, pages, self.ranges.clone())
+    }
+    fn respects_part_size(&self) -> bool {
+        false
+    }
+    fn expected_digest(&self) -> Option<&str> {
+        Some(&self.digest)
+    }
+}
+
+/// The routine argument is `<css path>[;<family>]`; the family is required when the
+/// stylesheet declares several.
+pub struct FromCss {
+    ranges: Vec<URange>,
+}
+
+impl FromCss {
+    fn new(arg: &RoutineArg) -> Result<Self> {
+        let arg = arg.required()?;
+        let (path, family) = match arg.split_once(';') {
+            Some((path, family)) => (path, Some(family.trim())),
+            None => (arg, None),
+        };
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read css {}: {}", path, e))?;
+        Ok(Self {
+            ranges: Self::parse(&content, family).map_err(|e| anyhow!("{}: {}", path, e))?,
+        })
+    }
+
+    fn parse(input: &str, family: Option<&str>) -> Result<Vec<URange>> {
+        let input = strip_comments(input);
+        let mut families = Vec::<String>::new();
+        let mut ranges = Vec::<URange>::new();
+        for block in input.split("@font-face").skip(1) {
+            let Some(body) = block
+                .split_once('{')
+                .and_then(|(_, rest)| rest.split_once('}'))
+                .map(|(body, _)| body)
+            else {
+                bail!("unterminated @font-face block");
+            };
+            let mut block_family = None;
+            let mut block_ranges = vec![];
+            for decl in body.split(';') {
+                let Some((prop, value)) = decl.split_once(':') else {
+                    continue;
+                };
+                let prop = prop.trim();
+                if prop.eq_ignore_ascii_case("font-family") {
+                    block_family = first_family(value);
+                } else if prop.eq_ignore_ascii_case("unicode-range") {
+                    block_ranges.push(URangeBuilder::from_css_syntax(value)?.build());
+                }
+            }
+            let block_family = block_family.unwrap_or_default();
+            if let Some(family) = family {
+                if !block_family.eq_ignore_ascii_case(family) {
+                    continue;
+                }
+            } else if !families
+                .iter()
+                .any(|f| f.eq_ignore_ascii_case(&block_family))
+            {
+                families.push(block_family);
+            }
+            for range in block_ranges {
+                if !ranges.contains(&range) {
+                    ranges.push(range);
+                }
+            }
+        }
+        if families.len() > 1 {
+            bail!(
+                "several families are declared ({}), pick one with `<css path>;<family>`",
+                families.join(", ")
+            );
+        }
+        if let (Some(family), true) = (family, ranges.is_empty()) {
+            bail!("no unicode-range declared for family {}", family);
         }
+        Ok(ranges)
+    }
+}
+
+impl AlgorithmImpl for FromCss {
+    fn partition(
+        &self,
+        config: &AlgorithmContext,
+        pages: Option<Cow<[Page]>>,
+    ) -> Result<Vec<URange>> {
+        with_residual(config, pages, self.ranges.clone())
+    }
+    fn respects_part_size(&self) -> bool {
+        false
     }
 }
 
@@ -161,5 +385,90 @@ pub(crate) static ALGORITHM_REGISTRY: LazyLock<Registry<AlgorithmContext, dyn Al
                 "sort_by_occurrence",
                 factory!(SortByOccurrence::new, [context]?),
             )
+            .add("from_manifest", factory!(FromManifest::new, [arg]?))
+            .add("from_css", factory!(FromCss::new, [arg]?))
             .with_default(routine!("sort_by_occurrence"))
     });
+
+#[test]
+fn test_from_css_parse() {
+    let css = r#"
+        /* [0] */
+        @font-face {
+            font-family: "A";
+            src: url(a0.woff2) format("woff2");
+            unicode-range: U+4e00-4e05, U+41;
+        }
+        @font-face {
+            font-family: "B";
+            UNICODE-RANGE: U+42;
+        }
+        @font-face { font-family: "A"; unicode-range: U+3??; }
+    "#;
+    let parse = |family| {
+        FromCss::parse(css, family)
+            .map(|ranges| ranges.iter().map(ToString::to_string).collect::<Vec<_>>())
+    };
+    assert_eq!(parse(Some("a")).unwrap(), ["U+41,U+4e00-4e05", "U+300-3ff"]);
+    assert_eq!(parse(Some("B")).unwrap(), ["U+42"]);
+    assert!(parse(None)
+        .unwrap_err()
+        .to_string()
+        .contains("several families"));
+    assert!(parse(Some("C")).is_err());
+}
+
+#[test]
+fn test_imported_residual() {
+    let config = AlgorithmContext {
+        part_size: PartSize::Chars(2),
+        char_base: None,
+        char_freq: None,
+        pages: None,
+    };
+    let pages = vec!["abcde".chars().collect::<Page>()];
+    let imported = FromCss {
+        ranges: vec![URangeBuilder::from_chars("bd".chars()).build()],
+    };
+    let ranges = imported
+        .partition(&config, Some(Cow::Owned(pages)))
+        .unwrap();
+    assert_eq!(
+        ranges
+            .iter()
+            .map(|r| r.as_chars().collect::<String>())
+            .collect::<Vec<_>>(),
+        ["bd", "ac", "e"]
+    );
+}
+
+#[test]
+fn test_pages_outside_char_freq() {
+    let pages = vec!["xa".chars().collect::<Page>()];
+    let char_freq = ['a', 'b'];
+    let ranges = SortByOccurrence::case_with_pages_generic(
+        PartSize::Chars(10),
+        Cow::Owned(pages),
+        Some(Cow::Borrowed(&char_freq[..])),
+        None,
+    );
+    let chars = ranges
+        .iter()
+        .flat_map(|r| r.as_chars())
+        .collect::<HashSet<_>>();
+    assert_eq!(chars, HashSet::from(['a', 'b', 'x']));
+}
+
+#[test]
+fn test_chunk_sizes() {
+    assert_eq!(chunk_sizes(PartSize::Chars(4), 10), [4, 4, 2]);
+    assert_eq!(chunk_sizes(PartSize::Chars(200), 10), [10]);
+    assert_eq!(chunk_sizes(PartSize::Count(3), 10), [4, 3, 3]);
+    assert_eq!(chunk_sizes(PartSize::Count(3), 2), [1, 1]);
+    let tiered = PartSize::Tiered {
+        first: 2,
+        growth: 2.0,
+        max: Some(5),
+    };
+    assert_eq!(chunk_sizes(tiered, 20), [2, 4, 5, 5, 4]);
+}
@@ -1,7 +1,7 @@
 use std::{
     borrow::Cow,
     collections::HashSet,
-    sync::{Arc, LazyLock, OnceLock},
+    sync::{LazyLock, OnceLock},
 };
 
 use allsorts::{
@@ -10,40 +10,50 @@ use allsorts::{
     tables::{cmap::CmapSubtable, FontTableProvider},
     Font,
 };
-use anyhow::{bail, Result};
-use fontchan_util::{autobox, factory, routine, LazyFile, Opt, Registry};
+use anyhow::{anyhow, bail, Result};
+use fontchan_util::{autobox, factory, routine, Opt, Registry};
 
-use crate::config::Context;
+use crate::config::{Context, FontFile};
 
 pub trait CharBaseProvider {
-    fn char_base(&self) -> Cow<HashSet<char>>;
+    fn char_base(&self) -> Result<Cow<HashSet<char>>>;
 }
 autobox!(CharBaseProvider);
 
 struct FromFonts {
-    fonts: Vec<Arc<LazyFile>>,
+    fonts: Vec<FontFile>,
     cache: OnceLock<HashSet<char>>,
 }
 
 impl CharBaseProvider for FromFonts {
-    fn char_base(&self) -> Cow<HashSet<char>> {
-        let chars = self.cache.get_or_init(|| {
-            let mut chars = HashSet::new();
-            for font in &self.fonts {
-                let buffer = font.content().unwrap();
-                let scope = ReadScope::new(&buffer);
-                let font_file = scope.read::<FontData>().unwrap();
-                let table_provider = font_file.table_provider(0).unwrap();
-                let mut font = allsorts::font::Font::new(Box::new(table_provider)).unwrap();
-                dump_cmap(&mut font, &mut chars).unwrap();
-            }
-            chars
-        });
-        Cow::Borrowed(chars)
+    fn char_base(&self) -> Result<Cow<HashSet<char>>> {
+        if let Some(chars) = self.cache.get() {
+            return Ok(Cow::Borrowed(chars));
+        }
+        let mut chars = HashSet::new();
+        for font in &self.fonts {
+            Self::read_font(font, &mut chars).map_err(|e| {
+                anyhow!(
+                    "cannot read cmap of font {}: {}",
+                    font.file.path().display(),
+                    e
+                )
+            })?;
+        }
+        Ok(Cow::Borrowed(self.cache.get_or_init(|| chars)))
     }
 }
 
 impl FromFonts {
+    fn read_font(font: &FontFile, chars: &mut HashSet<char>) -> Result<()> {
+        let buffer = font.file.content().map_err(|e| anyhow!("{}", e))?;
+        let scope = ReadScope::new(buffer);
+        let font_file = scope.read::<FontData>()?;
+        let table_provider = font_file.table_provider(font.face_index as usize)?;
+        let mut font = allsorts::font::Font::new(Box::new(table_provider))?;
+        dump_cmap(&mut font, chars)
+    }
+
     pub fn new(context: &Context) -> Self {
         Self {
             fonts: context.font_files.clone(),
@@ -62,7 +72,9 @@ fn dump_cmap<T: FontTableProvider>(font: &mut Font<T>, chars: &mut HashSet<char>
     }
 
     cmap_subtable.mappings_fn(|ch, _| {
-        chars.insert(ch.try_into().unwrap());
+        if let Some(ch) = char::from_u32(ch) {
+            chars.insert(ch);
+        }
     })?;
 
     Ok(())
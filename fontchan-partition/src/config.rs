@@ -1,12 +1,83 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
-use fontchan_util::{Con, LazyFile, Opt, Routine};
+use fontchan_util::{Con, Hasher, LazyFile, Multi, Opt, Routine, UpdateInto};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, Copy, Clone)]
-#[serde(untagged)]
+#[serde(try_from = "PartSizeRepr")]
 pub enum PartSize {
+    /// Chunks of `n` characters each.
     Chars(usize),
+    /// Exactly `n` chunks of (almost) equal size.
+    Count(usize),
+    /// Chunks starting from `first` characters and growing by `growth` each time, up to `max`.
+    Tiered {
+        first: usize,
+        growth: f64,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+enum PartSizeRepr {
+    Chars(usize),
+    Count {
+        count: usize,
+    },
+    Tiered {
+        first: usize,
+        #[serde(default = "PartSizeRepr::default_growth")]
+        growth: f64,
+        max: Option<usize>,
+    },
+}
+
+impl PartSizeRepr {
+    fn default_growth() -> f64 {
+        2.0
+    }
+}
+
+impl TryFrom<PartSizeRepr> for PartSize {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: PartSizeRepr) -> anyhow::Result<Self> {
+        Ok(match repr {
+            PartSizeRepr::Chars(num) => Self::Chars(num),
+            PartSizeRepr::Count { count } => Self::Count(count),
+            PartSizeRepr::Tiered { first, growth, max } => {
+                if first == 0 {
+                    anyhow::bail!("tiered part size must start from at least 1 character");
+                }
+                if growth.is_nan() || growth < 1.0 {
+                    anyhow::bail!("tiered part size must not shrink, got growth {}", growth);
+                }
+                Self::Tiered { first, growth, max }
+            }
+        })
+    }
+}
+
+impl UpdateInto for PartSize {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        match self {
+            PartSize::Chars(num) => {
+                hasher.update(b"chars");
+                hasher.update(&num.to_le_bytes());
+            }
+            PartSize::Count(count) => {
+                hasher.update(b"count");
+                hasher.update(&count.to_le_bytes());
+            }
+            PartSize::Tiered { first, growth, max } => {
+                hasher.update(b"tiered");
+                hasher.update(&first.to_le_bytes());
+                hasher.update(&growth.to_le_bytes());
+                hasher.update(&max.unwrap_or(0).to_le_bytes());
+            }
+        }
+    }
 }
 
 impl Default for PartSize {
@@ -23,12 +94,111 @@ pub struct Config {
 
     pub char_base: Option<Con<Routine, Opt>>,
     pub char_freq: Option<Con<Routine, Opt>>,
-    pub pages: Option<Con<Routine, Opt>>,
+    /// The pages, e.g., `"glob[public/**/*.html]"`, or a list of them to combine.
+    pub pages: Option<Con<Routine, Multi>>,
+    #[serde(default)]
+    pub scan: ScanConfig,
+    /// Expands the characters of the pages with their Simplified/Traditional
+    /// and semantic variants.
+    #[serde(default)]
+    pub han_variants: bool,
 
     pub algorithm: Option<Con<Routine>>,
+
+    pub manifest: Option<PathBuf>,
 }
 
 #[derive(Default)]
 pub struct Context {
-    pub font_files: Vec<Arc<LazyFile>>,
+    pub font_files: Vec<FontFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FontFile {
+    pub file: Arc<LazyFile>,
+    /// Index of the face in a font collection, i.e., TTC or OTC.
+    pub face_index: u32,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Encoding of pages without a BOM or `<meta charset>`. Defaults to UTF-8.
+    pub encoding: Option<String>,
+    /// Which characters of string literals to keep when scanning JS and CSS assets.
+    #[serde(default)]
+    pub literals: LiteralFilter,
+    /// File caching the characters of the scanned files, relative to the work dir.
+    pub cache: Option<PathBuf>,
+    /// How much each chapter of an EPUB counts as a page.
+    #[serde(default)]
+    pub chapter_weight: ChapterWeight,
+    /// Adds the characters of the text normalized in this form, e.g., for pages in NFD.
+    pub normalization: Option<Normalization>,
+    /// Adds the variants of the characters that CSS may render instead,
+    /// e.g., with `text-transform: uppercase` or `full-width`.
+    #[serde(default)]
+    pub variants: Vec<Variant>,
+}
+
+/// Leaves out `cache`, which does not change the scanned characters.
+impl UpdateInto for &ScanConfig {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        hasher.update(self.encoding.as_deref().unwrap_or_default().as_bytes());
+        let options = format!(
+            "{:?} {:?} {:?} {:?}",
+            self.literals, self.chapter_weight, self.normalization, self.variants
+        );
+        hasher.update(options.as_bytes());
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    Nfc,
+    Nfkc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// Upper and lower cases.
+    Case,
+    /// Full-width forms of ASCII.
+    Width,
+}
+
+#[derive(Debug, Default, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChapterWeight {
+    /// Every chapter counts as one page.
+    #[default]
+    Uniform,
+    /// A chapter counts as one page per thousand characters.
+    Length,
+}
+
+#[derive(Debug, Default, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiteralFilter {
+    #[default]
+    All,
+    NonAscii,
+    Cjk,
+}
+
+#[test]
+fn test_tiered_part_size() {
+    let parse = |json| serde_json::from_value::<PartSize>(json);
+    assert!(matches!(
+        parse(serde_json::json!({"first": 10})).unwrap(),
+        PartSize::Tiered {
+            first: 10,
+            growth: 2.0,
+            max: None
+        }
+    ));
+    assert!(parse(serde_json::json!({"first": 0})).is_err());
+    assert!(parse(serde_json::json!({"first": 10, "growth": 0.5})).is_err());
 }
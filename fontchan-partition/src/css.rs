@@ -0,0 +1,21 @@
+/// Removes the `/* ... */` comments of a stylesheet.
+pub(crate) fn strip_comments(mut input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    while let Some((head, tail)) = input.split_once("/*") {
+        out.push_str(head);
+        input = tail.split_once("*/").map_or("", |(_, rest)| rest);
+    }
+    out.push_str(input);
+    out
+}
+
+/// Parses the first family of a `font-family` value.
+pub(crate) fn first_family(value: &str) -> Option<String> {
+    let value = value.trim().trim_end_matches("!important");
+    let family = value.split(',').next()?.trim();
+    let family = family.trim_matches(|c| c == '"' || c == '\'').trim();
+    match family.to_ascii_lowercase().as_str() {
+        "" | "inherit" | "initial" | "unset" | "revert" => None,
+        _ => Some(family.to_owned()),
+    }
+}
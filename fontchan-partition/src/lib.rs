@@ -2,23 +2,167 @@ mod algorithms;
 mod char_base;
 mod char_freq;
 mod config;
+mod css;
+mod manifest;
 mod pages;
+mod validate;
+
+use std::{borrow::Cow, collections::HashSet};
 
 use algorithms::*;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 pub use config::*;
 use fontchan_unicode::URange;
+use fontchan_util::{DigestString, Digester};
+pub use manifest::Manifest;
+pub use pages::Page;
+pub use validate::ValidationReport;
 
 pub struct Algorithm {
     ctx: AlgorithmContext,
     impl_: Box<dyn AlgorithmImpl>,
+    id: String,
+    digest: DigestString,
 }
 
 impl Algorithm {
-    pub fn partition(&self) -> Vec<URange> {
-        self.impl_.partition(&self.ctx)
+    pub fn partition(&self) -> Result<Vec<URange>> {
+        self.partition_with(self.pages())
+    }
+    /// Partitions for the font of `family`, with the pages of [`Self::pages_for`].
+    pub fn partition_for(&self, family: &str) -> Result<Vec<URange>> {
+        self.partition_with(self.pages_for(family))
+    }
+    /// The pages attributed to `family`, matched case-insensitively, and those
+    /// attributed to no family.
+    pub fn pages_for(&self, family: &str) -> Option<Cow<[Page]>> {
+        self.pages().map(|pages| {
+            let pages = pages
+                .iter()
+                .filter(|page| page.family().is_none_or(|f| f.eq_ignore_ascii_case(family)))
+                .cloned()
+                .collect::<Vec<_>>();
+            Cow::Owned(pages)
+        })
+    }
+    fn partition_with(&self, pages: Option<Cow<[Page]>>) -> Result<Vec<URange>> {
+        if let Some(expected) = self.impl_.expected_digest() {
+            if expected != &*self.digest() {
+                eprintln!(
+                    "warning: {}: made from other fonts, pages or config, regenerate it",
+                    self.id
+                );
+            }
+        }
+        let ranges = self
+            .impl_
+            .partition(&self.ctx, pages.as_deref().map(Cow::Borrowed))?;
+        let report = self.validate_with(&ranges, pages.as_deref())?;
+        if !report.is_ok() {
+            bail!("{}: {}", self.id, report);
+        }
+        Ok(ranges)
+    }
+    pub fn pages(&self) -> Option<Cow<[Page]>> {
+        self.ctx.pages.as_ref().map(|p| p.pages())
+    }
+    pub fn char_base(&self) -> Result<Option<Cow<HashSet<char>>>> {
+        self.ctx
+            .char_base
+            .as_ref()
+            .map(|p| p.char_base())
+            .transpose()
+    }
+    pub fn validate(&self, ranges: &[URange]) -> Result<ValidationReport> {
+        self.validate_with(ranges, self.pages().as_deref())
+    }
+    /// Checks coverage of the char base, or, without one, of the chars of `pages`.
+    fn validate_with(&self, ranges: &[URange], pages: Option<&[Page]>) -> Result<ValidationReport> {
+        let coverage = match self.char_base()? {
+            Some(char_base) => Some(validate::Coverage::CharBase(char_base)),
+            None => pages.map(|pages| {
+                let chars = pages.iter().flat_map(|page| page.chars()).cloned();
+                validate::Coverage::Pages(chars.collect())
+            }),
+        };
+        let limit = self.impl_.respects_part_size().then(|| {
+            let len = ranges.iter().map(|r| r.as_chars().count()).sum();
+            chunk_size_limit(self.ctx.part_size, len)
+        });
+        Ok(validate::validate(
+            ranges,
+            coverage.as_ref(),
+            limit.flatten(),
+        ))
+    }
+    /// Digest of the inputs, i.e., the config, the fonts and the characters of the pages.
+    pub fn digest(&self) -> DigestString {
+        let mut digester = Digester::new().push(&*self.digest);
+        let Some(pages) = self.pages() else {
+            return digester.base64_result();
+        };
+        // The pages are scanned in parallel, hence in no particular order.
+        let mut pages = pages
+            .iter()
+            .map(|page| {
+                let mut chars = page.chars().iter().collect::<Vec<_>>();
+                chars.sort_unstable();
+                let chars = chars.into_iter().collect::<String>();
+                (chars, page.weight(), page.family().unwrap_or_default())
+            })
+            .collect::<Vec<_>>();
+        pages.sort_unstable();
+        for (chars, weight, family) in pages {
+            digester = digester
+                .push(chars.len().to_le_bytes())
+                .push(chars)
+                .push(weight.to_le_bytes())
+                .push(family);
+        }
+        digester.base64_result()
+    }
+    pub fn to_manifest(&self, ranges: &[URange]) -> Manifest {
+        Manifest {
+            algorithm: self.id.clone(),
+            digest: self.digest().to_string(),
+            ranges: ranges.to_vec(),
+        }
+    }
+}
+
+fn inputs_digest(context: &Context, config: &Config) -> Result<DigestString> {
+    let mut digester = Digester::new().push(config.part_size);
+    let routines = [
+        (
+            "char_base",
+            char_base::CHAR_BASE_REGISTRY.resolve(&config.char_base),
+        ),
+        (
+            "char_freq",
+            char_freq::CHAR_FREQ_REGISTRY.resolve(&config.char_freq),
+        ),
+    ];
+    for (key, routine) in routines {
+        if let Some(routine) = routine {
+            digester = digester.push(key).push(routine);
+        }
+    }
+    if let Some(routines) = pages::PAGES_REGISTRY.resolve(&config.pages) {
+        digester = digester.push("pages").push(routines);
     }
+    if config.han_variants {
+        digester = digester.push("han_variants");
+    }
+    digester = digester.push("scan").push(&config.scan);
+    for font in &context.font_files {
+        let digest = font
+            .file
+            .digest()
+            .map_err(|e| anyhow!("cannot read font {}: {}", font.file.path().display(), e))?;
+        digester = digester.push(digest).push(font.face_index.to_le_bytes());
+    }
+    Ok(digester.base64_result())
 }
 
 pub fn build_algorithm(context: &Context, config: &Config) -> Result<Algorithm> {
@@ -28,9 +172,19 @@ pub fn build_algorithm(context: &Context, config: &Config) -> Result<Algorithm>
     let char_freq = char_freq::CHAR_FREQ_REGISTRY
         .build(context, &config.char_freq)?
         .into_data();
-    let pages = pages::PAGES_REGISTRY
-        .build(context, &config.pages)?
-        .into_data();
+    let pages_ctx = pages::PagesContext {
+        scan: config.scan.clone(),
+    };
+    let pages = match config.pages {
+        Some(_) => pages::PAGES_REGISTRY
+            .build(&pages_ctx, &config.pages)?
+            .into_data(),
+        None => vec![],
+    };
+    let pages = pages::MergedPagesProvider::merge(pages).map(|pages| match config.han_variants {
+        true => Box::new(pages::HanVariantsPagesProvider::new(pages)),
+        false => pages,
+    });
     let algo_ctx = AlgorithmContext {
         part_size: config.part_size,
         char_base,
@@ -40,9 +194,18 @@ pub fn build_algorithm(context: &Context, config: &Config) -> Result<Algorithm>
     let impl_ = ALGORITHM_REGISTRY
         .build(&algo_ctx, &config.algorithm)?
         .into_data();
+    let id = ALGORITHM_REGISTRY
+        .resolve(&config.algorithm)
+        .ok_or_else(|| anyhow!("routine required"))?
+        .map_ref(ToString::to_string)
+        .collect()
+        .into_data();
+    let digest = inputs_digest(context, config)?;
     Ok(Algorithm {
         ctx: algo_ctx,
         impl_,
+        id,
+        digest,
     })
 }
 
@@ -52,7 +215,10 @@ fn test() {
     use std::sync::Arc;
 
     let context = Context {
-        font_files: vec![Arc::new("samples/LXGWWenKaiGB-Regular.woff".into())],
+        font_files: vec![FontFile {
+            file: Arc::new("samples/LXGWWenKaiGB-Regular.woff".into()),
+            face_index: 0,
+        }],
         ..Default::default()
     };
     let config = Config {
@@ -60,12 +226,65 @@ fn test() {
         char_base: Some(routine!("from_fonts").into()),
         char_freq: Some(routine!("preset_zh").into()),
         pages: Some(routine!("glob[../../hsfzxjy.github.io/public/**/*.html]").into()),
+        scan: Default::default(),
+        han_variants: false,
         algorithm: Default::default(),
+        manifest: None,
     };
     let algo = build_algorithm(&context, &config).unwrap();
-    let res = algo.partition();
+    let res = algo.partition().unwrap();
     dbg!(res[0..10]
         .iter()
         .map(|x| x.as_chars().collect::<String>())
         .collect::<Vec<_>>());
 }
+
+#[test]
+fn test_combined_pages() {
+    let dir = std::env::temp_dir().join(format!("fontchan-pages-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("index.html"), "<p>你好</p>").unwrap();
+    std::fs::write(dir.join("zh.po"), "msgid \"Bye\"\nmsgstr \"再见\"\n").unwrap();
+    let config: Config = serde_json::from_value(serde_json::json!({
+        "pages": [
+            format!("glob[{}/*.html]", dir.display()),
+            format!("locale[{}/*.po]", dir.display()),
+        ],
+    }))
+    .unwrap();
+    let algo = build_algorithm(&Default::default(), &config).unwrap();
+    let chars = algo
+        .pages()
+        .unwrap()
+        .iter()
+        .flat_map(|page| page.chars().clone())
+        .collect::<HashSet<_>>();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!("你好再见".chars().all(|ch| chars.contains(&ch)));
+}
+
+#[test]
+fn test_pages_for_family() {
+    let dir = std::env::temp_dir().join(format!("fontchan-families-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("site.css"),
+        "p { font-family: \"Text Sans\" } h1 { font-family: Display }",
+    )
+    .unwrap();
+    std::fs::write(dir.join("index.html"), "<h1>标题</h1><p>正文</p>").unwrap();
+    let config: Config = serde_json::from_value(serde_json::json!({
+        "pages": format!("cascade[{0}/*.css;{0}/*.html]", dir.display()),
+    }))
+    .unwrap();
+    let algo = build_algorithm(&Default::default(), &config).unwrap();
+    let chars = algo
+        .pages_for("text sans")
+        .unwrap()
+        .iter()
+        .flat_map(|page| page.chars().clone())
+        .collect::<HashSet<_>>();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!("正文".chars().all(|ch| chars.contains(&ch)));
+    assert!(!chars.contains(&'标'));
+}
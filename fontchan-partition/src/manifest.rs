@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use fontchan_unicode::{URange, URangeBuilder};
+use fontchan_util::AtomicPath;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub algorithm: String,
+    pub digest: String,
+    #[serde(with = "css_ranges")]
+    pub ranges: Vec<URange>,
+}
+
+impl Manifest {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read manifest {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("invalid manifest {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        let dest = AtomicPath::from(path.as_ref()).into_writable()?;
+        std::fs::write(dest.as_ref(), content.as_bytes())?;
+        dest.commit()?;
+        Ok(())
+    }
+}
+
+mod css_ranges {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ranges: &[URange], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ranges.iter().map(ToString::to_string))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<URange>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|input| {
+                URangeBuilder::from_css_syntax(input)
+                    .map(URangeBuilder::build)
+                    .map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,282 @@
+use std::{borrow::Cow, cell::Cell, path::Path, sync::OnceLock};
+
+use anyhow::Result;
+use fontchan_util::RoutineArg;
+
+use super::{decode, scan_glob, Page, PagesContext, PagesProvider, ScanCache, TextTransform};
+use crate::config::LiteralFilter;
+
+/// Reads string literals from JS and CSS assets, e.g., toasts in scripts
+/// and `content:` properties in stylesheets.
+pub struct AssetsPagesProvider {
+    cache: OnceLock<Vec<Page>>,
+    glob: Cell<Option<glob::Paths>>,
+    encoding: &'static encoding_rs::Encoding,
+    filter: LiteralFilter,
+    text: TextTransform,
+    scan_cache: Option<ScanCache>,
+}
+
+impl AssetsPagesProvider {
+    pub(super) fn new(context: &PagesContext, pattern: &RoutineArg) -> Result<Self> {
+        let pattern = pattern.required()?;
+        Ok(Self {
+            cache: OnceLock::new(),
+            glob: Cell::new(Some(glob::glob(pattern)?)),
+            encoding: decode::parse_encoding(context.scan.encoding.as_deref())?,
+            filter: context.scan.literals,
+            text: TextTransform::new(&context.scan),
+            scan_cache: ScanCache::open(context, "assets", &[]),
+        })
+    }
+
+    fn read_file(
+        path: &Path,
+        bytes: &[u8],
+        encoding: &'static encoding_rs::Encoding,
+        filter: LiteralFilter,
+        text: TextTransform,
+    ) -> Result<Vec<Page>> {
+        let content = decode::decode_page(bytes, encoding)?;
+        let is_css = path.extension().is_some_and(|ext| {
+            let ext = ext.to_string_lossy().to_ascii_lowercase();
+            matches!(ext.as_str(), "css" | "scss" | "sass" | "less")
+        });
+        let literals = if is_css {
+            css_literals(&content)
+        } else {
+            js_literals(&content)
+        };
+        let page = text
+            .chars(&literals.join("\n"))
+            .into_iter()
+            .filter(|ch| filter.accepts(*ch))
+            .collect::<Page>();
+        Ok(vec![page.with_path(path.to_owned())])
+    }
+}
+
+impl PagesProvider for AssetsPagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let (encoding, filter, text) = (self.encoding, self.filter, self.text);
+        let pages = self.cache.get_or_init(|| {
+            scan_glob(
+                self.glob.take().unwrap(),
+                self.scan_cache.as_ref(),
+                |path, bytes| Self::read_file(path, bytes, encoding, filter, text),
+            )
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+impl LiteralFilter {
+    fn accepts(self, ch: char) -> bool {
+        match self {
+            LiteralFilter::All => true,
+            LiteralFilter::NonAscii => !ch.is_ascii(),
+            LiteralFilter::Cjk => is_cjk(ch),
+        }
+    }
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x2E80..=0x2FDF     // radicals
+        | 0x3000..=0x312F   // symbols and punctuation, kana, bopomofo
+        | 0x3190..=0x31FF   // kanbun, strokes, katakana extensions
+        | 0x3400..=0x4DBF   // extension A
+        | 0x4E00..=0x9FFF   // unified ideographs
+        | 0xAC00..=0xD7AF   // hangul syllables
+        | 0xF900..=0xFAFF   // compatibility ideographs
+        | 0xFE30..=0xFE4F   // compatibility forms
+        | 0xFF00..=0xFFEF   // half- and full-width forms
+        | 0x20000..=0x3134F // extensions B and later
+    )
+}
+
+/// Reads `\u{...}`-style hex digits from `chars`, consuming at most `max` digits.
+fn take_hex(chars: &mut std::iter::Peekable<std::str::Chars>, max: usize) -> Option<char> {
+    let mut value = 0u32;
+    let mut n = 0;
+    while n < max {
+        let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) else {
+            break;
+        };
+        value = value * 16 + digit;
+        chars.next();
+        n += 1;
+    }
+    (n > 0).then(|| char::from_u32(value)).flatten()
+}
+
+fn js_literals(source: &str) -> Vec<String> {
+    let mut literals = vec![];
+    let mut chars = source.chars().peekable();
+    // Depths of `${` nesting for each enclosing template literal.
+    let mut templates = Vec::<usize>::new();
+    let mut depth = 0usize;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '\'' | '"' => literals.push(js_string(&mut chars, ch)),
+            '`' => {
+                let (text, interpolated) = js_template(&mut chars);
+                literals.push(text);
+                if interpolated {
+                    templates.push(depth);
+                    depth += 1;
+                }
+            }
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if templates.last() == Some(&depth) {
+                    templates.pop();
+                    let (text, interpolated) = js_template(&mut chars);
+                    literals.push(text);
+                    if interpolated {
+                        templates.push(depth);
+                        depth += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    literals
+}
+
+fn js_escape(chars: &mut std::iter::Peekable<std::str::Chars>, out: &mut String) {
+    match chars.next() {
+        Some('n') => out.push('\n'),
+        Some('t') => out.push('\t'),
+        Some('r') | Some('\n') => {}
+        Some('x') => out.extend(take_hex(chars, 2)),
+        Some('u') if chars.peek() == Some(&'{') => {
+            chars.next();
+            out.extend(take_hex(chars, 6));
+            chars.next_if_eq(&'}');
+        }
+        Some('u') => out.extend(take_hex(chars, 4)),
+        Some(c) => out.push(c),
+        None => {}
+    }
+}
+
+fn js_string(chars: &mut std::iter::Peekable<std::str::Chars>, quote: char) -> String {
+    let mut out = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => js_escape(chars, &mut out),
+            '\n' => break,
+            c if c == quote => break,
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reads a template literal up to the closing backtick or a `${`,
+/// returning the text and whether an interpolation starts.
+fn js_template(chars: &mut std::iter::Peekable<std::str::Chars>) -> (String, bool) {
+    let mut out = String::new();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => js_escape(chars, &mut out),
+            '`' => return (out, false),
+            '$' if chars.next_if_eq(&'{').is_some() => return (out, true),
+            c => out.push(c),
+        }
+    }
+    (out, false)
+}
+
+pub(super) fn css_literals(source: &str) -> Vec<String> {
+    let mut literals = vec![];
+    let mut chars = source.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = '\0';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '\'' | '"' => {
+                let mut out = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match take_hex(&mut chars, 6) {
+                            Some(decoded) => {
+                                out.push(decoded);
+                                chars.next_if(|c| c.is_whitespace());
+                            }
+                            None => out.extend(chars.next().filter(|c| *c != '\n')),
+                        },
+                        '\n' => break,
+                        c if c == ch => break,
+                        c => out.push(c),
+                    }
+                }
+                literals.push(out);
+            }
+            _ => {}
+        }
+    }
+    literals
+}
+
+#[test]
+fn test_literals() {
+    let js = r#"
+        // 'not this'
+        /* "nor this" */
+        toast('保存成功');
+        const s = "你\u{597d}\x41\"";
+        const t = `共 ${count > 1 ? `${count} 项` : "一项"} 结果`;
+    "#;
+    assert_eq!(
+        js_literals(js),
+        ["保存成功", "你好A\"", "共 ", "", " 项", "一项", " 结果"]
+    );
+
+    let css =
+        r#"/* "x" */ .a::before { content: "\e001 \201C引"; } .b { font-family: 'Noto Sans'; }"#;
+    assert_eq!(css_literals(css), ["\u{e001}\u{201c}引", "Noto Sans"]);
+    assert!(LiteralFilter::Cjk.accepts('引') && !LiteralFilter::Cjk.accepts('\u{e001}'));
+}
+
+#[test]
+fn test_read_file_encoding() {
+    let text = TextTransform::new(&Default::default());
+    let read = |bytes: &[u8], encoding| {
+        AssetsPagesProvider::read_file(
+            Path::new("app.js"),
+            bytes,
+            encoding,
+            LiteralFilter::Cjk,
+            text,
+        )
+    };
+    let (gbk, _, _) = encoding_rs::GBK.encode("toast('中文');");
+    assert!(read(&gbk, encoding_rs::UTF_8).is_err());
+    let pages = read(&gbk, encoding_rs::GBK).unwrap();
+    assert_eq!(pages[0].chars(), &"中文".chars().collect());
+}
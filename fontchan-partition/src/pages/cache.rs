@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Result;
+use fontchan_util::{AtomicPath, Digester};
+use serde::{Deserialize, Serialize};
+
+use super::{Page, PagesContext};
+
+/// Bumped whenever the extraction of any provider changes.
+const VERSION: &str = "3";
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    mode: String,
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    mtime: u64,
+    size: u64,
+    digest: String,
+    pages: Vec<CachedPage>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedPage {
+    path: Option<PathBuf>,
+    chars: String,
+    weight: u32,
+    family: Option<String>,
+}
+
+impl From<&Page> for CachedPage {
+    fn from(page: &Page) -> Self {
+        let mut chars = page.chars.iter().copied().collect::<Vec<_>>();
+        chars.sort_unstable();
+        Self {
+            path: page.path.clone(),
+            chars: chars.into_iter().collect(),
+            weight: page.weight,
+            family: page.family.clone(),
+        }
+    }
+}
+
+impl From<&CachedPage> for Page {
+    fn from(cached: &CachedPage) -> Self {
+        Self {
+            path: cached.path.clone(),
+            chars: cached.chars.chars().collect(),
+            weight: cached.weight,
+            family: cached.family.clone(),
+        }
+    }
+}
+
+/// Persists the pages read from each file, so that unchanged files are
+/// neither read nor parsed again.
+///
+/// A file is unchanged if its mtime and size are the same, or else if its
+/// content digest is. The whole cache is dropped once the extraction mode,
+/// i.e., the provider, its argument or the scan config, changes.
+pub(super) struct ScanCache {
+    path: PathBuf,
+    mode: String,
+    entries: HashMap<PathBuf, Entry>,
+    updated: Mutex<BTreeMap<PathBuf, Entry>>,
+}
+
+impl ScanCache {
+    pub(super) fn open(context: &PagesContext, kind: &str, extra: &[u8]) -> Option<Self> {
+        let path = context.scan.cache.clone()?;
+        let scan = &context.scan;
+        let mode = Digester::new()
+            .push(VERSION)
+            .push(kind)
+            .push(extra)
+            .push(format!(
+                "{:?}|{:?}|{:?}|{:?}|{:?}",
+                scan.encoding,
+                scan.literals,
+                scan.chapter_weight,
+                scan.normalization,
+                scan.variants
+            ))
+            .base64_result()
+            .as_ref()
+            .to_owned();
+        let entries = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|file| file.mode == mode)
+            .map(|file| file.entries.into_iter().collect())
+            .unwrap_or_default();
+        Some(Self {
+            path,
+            mode,
+            entries,
+            updated: Default::default(),
+        })
+    }
+
+    pub(super) fn read<F>(&self, path: &Path, read: F) -> Result<Vec<Page>>
+    where
+        F: Fn(&Path, &[u8]) -> Result<Vec<Page>>,
+    {
+        let metadata = std::fs::metadata(path)?;
+        let size = metadata.len();
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let cached = self.entries.get(path);
+        let entry = match cached {
+            Some(entry) if entry.mtime == mtime && entry.size == size => entry.clone(),
+            _ => {
+                let bytes = std::fs::read(path)?;
+                let digest = Digester::new()
+                    .push(&bytes)
+                    .base64_result()
+                    .as_ref()
+                    .to_owned();
+                match cached {
+                    Some(entry) if entry.size == size && entry.digest == digest => Entry {
+                        mtime,
+                        ..entry.clone()
+                    },
+                    _ => Entry {
+                        mtime,
+                        size,
+                        digest,
+                        pages: read(path, &bytes)?.iter().map(CachedPage::from).collect(),
+                    },
+                }
+            }
+        };
+        let pages = entry.pages.iter().map(Page::from).collect();
+        self.updated.lock().unwrap().insert(path.to_owned(), entry);
+        Ok(pages)
+    }
+
+    /// Saves the entries of the files read, dropping those of the others.
+    pub(super) fn save(&self) -> Result<()> {
+        let file = CacheFile {
+            mode: self.mode.clone(),
+            entries: std::mem::take(&mut *self.updated.lock().unwrap()),
+        };
+        let dest = AtomicPath::from(&self.path).into_writable()?;
+        std::fs::write(dest.as_ref(), serde_json::to_vec(&file)?)?;
+        dest.commit()?;
+        Ok(())
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[test]
+fn test_scan_cache() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let dir = std::env::temp_dir().join(format!("fontchan-scan-cache-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let page_path = dir.join("page.html");
+    std::fs::write(&page_path, "你好")?;
+    let mut context = PagesContext {
+        scan: Default::default(),
+    };
+    context.scan.cache = Some(dir.join("cache.json"));
+
+    let parsed = AtomicUsize::new(0);
+    let read = |_: &Path, bytes: &[u8]| -> Result<Vec<Page>> {
+        parsed.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![std::str::from_utf8(bytes)?.chars().collect()])
+    };
+    let scan = |kind: &str| -> Result<Vec<Page>> {
+        let cache = ScanCache::open(&context, kind, &[]).unwrap();
+        let pages = cache.read(&page_path, read)?;
+        cache.save()?;
+        Ok(pages)
+    };
+    assert_eq!(scan("glob")?[0].chars().len(), 2);
+    assert_eq!(scan("glob")?[0].chars().len(), 2);
+    assert_eq!(parsed.load(Ordering::SeqCst), 1);
+    scan("assets")?;
+    assert_eq!(parsed.load(Ordering::SeqCst), 2);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
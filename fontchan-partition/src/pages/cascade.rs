@@ -0,0 +1,480 @@
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::{anyhow, bail, Result};
+use fontchan_util::RoutineArg;
+
+use super::{decode, scan_glob, Page, PagesContext, PagesProvider, ScanCache, TextTransform};
+use crate::css::{first_family, strip_comments};
+
+/// Reads the text of HTML pages, attributing each text node to the first family
+/// of the `font-family` it is rendered with. A page is yielded per family, so that
+/// each font can be partitioned by the text it actually renders.
+///
+/// The `font-family` is resolved from the stylesheets, the `<style>` blocks and the
+/// inline styles of the page, with specificity, source order and inheritance.
+/// A page takes the stylesheets it links, in the order of its `<link>`s, or all of
+/// them in path order when it links none of them.
+/// Only simple selectors are supported, i.e., compounds of a type, an id and
+/// classes joined by descendant combinators, where `>` is taken as a descendant
+/// combinator. Selectors with pseudo-classes, attributes or sibling combinators
+/// are ignored.
+///
+/// The routine argument is `<stylesheets glob>;<pages glob>`.
+pub struct CascadePagesProvider {
+    cache: OnceLock<Vec<Page>>,
+    glob: Cell<Option<glob::Paths>>,
+    encoding: &'static encoding_rs::Encoding,
+    sheets: Vec<(PathBuf, Vec<Rule>)>,
+    text: TextTransform,
+    scan_cache: Option<ScanCache>,
+}
+
+impl CascadePagesProvider {
+    pub(super) fn new(context: &PagesContext, arg: &RoutineArg) -> Result<Self> {
+        let arg = arg.required()?;
+        let Some((stylesheets, pattern)) = arg.split_once(';') else {
+            bail!("expected `<stylesheets glob>;<pages glob>`, got {}", arg);
+        };
+        let mut paths = glob::glob(stylesheets)?.collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
+        let mut key = String::new();
+        let mut sheets = vec![];
+        for path in paths {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+            let path = path.canonicalize().unwrap_or(path);
+            key.push_str(&format!("{}\n{}\n", path.display(), content));
+            let mut rules = vec![];
+            parse_rules(&content, &mut rules);
+            sheets.push((path, rules));
+        }
+        Ok(Self {
+            cache: OnceLock::new(),
+            glob: Cell::new(Some(glob::glob(pattern)?)),
+            encoding: decode::parse_encoding(context.scan.encoding.as_deref())?,
+            sheets,
+            text: TextTransform::new(&context.scan),
+            scan_cache: ScanCache::open(context, "cascade", key.as_bytes()),
+        })
+    }
+
+    fn read_page(
+        path: &Path,
+        bytes: &[u8],
+        encoding: &'static encoding_rs::Encoding,
+        sheets: &[(PathBuf, Vec<Rule>)],
+        transform: TextTransform,
+    ) -> Result<Vec<Page>> {
+        let content = decode::decode_page(bytes, encoding)?;
+        let mut linked = linked_stylesheets(&content, path, sheets).peekable();
+        let rules = match linked.peek() {
+            Some(_) => linked.flatten().collect::<Vec<_>>(),
+            None => sheets.iter().flat_map(|(_, rules)| rules).collect(),
+        };
+        let mut texts = attribute_text(&content, &rules)
+            .into_iter()
+            .collect::<Vec<_>>();
+        texts.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let pages = texts
+            .into_iter()
+            .map(|(family, text)| {
+                let page = transform.page(&text).with_path(path.to_owned());
+                match family {
+                    Some(family) => page.with_family(family),
+                    None => page,
+                }
+            })
+            .collect();
+        Ok(pages)
+    }
+}
+
+impl PagesProvider for CascadePagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let pages = self.cache.get_or_init(|| {
+            let (encoding, sheets, transform) = (self.encoding, &self.sheets, self.text);
+            scan_glob(
+                self.glob.take().unwrap(),
+                self.scan_cache.as_ref(),
+                |path, bytes| Self::read_page(path, bytes, encoding, sheets, transform),
+            )
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Compound {
+    fn parse(input: &str) -> Option<Self> {
+        let split = |s: &str| s.find(['.', '#']).unwrap_or(s.len());
+        let mut compound = Self::default();
+        let end = split(input);
+        let tag = &input[..end];
+        if !tag.is_empty() && tag != "*" {
+            compound.tag = Some(tag.to_ascii_lowercase());
+        }
+        let mut rest = &input[end..];
+        while let Some(kind) = rest.chars().next() {
+            let body = &rest[1..];
+            let end = split(body);
+            let name = &body[..end];
+            if name.is_empty() {
+                return None;
+            }
+            match kind {
+                '.' => compound.classes.push(name.to_owned()),
+                _ => compound.id = Some(name.to_owned()),
+            }
+            rest = &body[end..];
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, element: &Element) -> bool {
+        self.tag.as_ref().is_none_or(|tag| *tag == element.tag)
+            && (self.id.is_none() || self.id == element.id)
+            && self.classes.iter().all(|c| element.classes.contains(c))
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    selector: Vec<Compound>,
+    specificity: (usize, usize, usize),
+    /// `None` for keywords like `inherit`.
+    family: Option<String>,
+}
+
+impl Rule {
+    /// Whether the last element of `stack` is matched, with the others as its ancestors.
+    fn matches(&self, stack: &[Element]) -> bool {
+        let Some((last, ancestors)) = self.selector.split_last() else {
+            return false;
+        };
+        let Some((element, mut parents)) = stack.split_last() else {
+            return false;
+        };
+        if !last.matches(element) {
+            return false;
+        }
+        for compound in ancestors.iter().rev() {
+            match parents.iter().rposition(|e| compound.matches(e)) {
+                Some(pos) => parents = &parents[..pos],
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+/// Finds the `font-family` declaration of a declaration block, and parses its first family.
+fn declared_family(body: &str) -> Option<Option<String>> {
+    body.split(';').rev().find_map(|decl| {
+        let (prop, value) = decl.split_once(':')?;
+        prop.trim()
+            .eq_ignore_ascii_case("font-family")
+            .then(|| first_family(value))
+    })
+}
+
+fn parse_rules(css: &str, rules: &mut Vec<Rule>) {
+    let css = strip_comments(css);
+    for block in css.split('}') {
+        let Some((prelude, body)) = block.rsplit_once('{') else {
+            continue;
+        };
+        // Skip the enclosing at-rules, e.g., `@media ... {`.
+        let prelude = prelude.rsplit('{').next().unwrap_or_default().trim();
+        if prelude.starts_with('@') {
+            continue;
+        }
+        let Some(family) = declared_family(body) else {
+            continue;
+        };
+        for selector in prelude.split(',') {
+            if selector.contains([':', '[', '+', '~']) {
+                continue;
+            }
+            let Some(selector) = selector
+                .replace('>', " ")
+                .split_whitespace()
+                .map(Compound::parse)
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let specificity = selector.iter().fold((0, 0, 0), |(a, b, c), compound| {
+                (
+                    a + compound.id.is_some() as usize,
+                    b + compound.classes.len(),
+                    c + compound.tag.is_some() as usize,
+                )
+            });
+            rules.push(Rule {
+                selector,
+                specificity,
+                family: family.clone(),
+            });
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    tag: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    family: Option<String>,
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Parses a start tag after its `<`, returning the name, the attributes,
+/// whether it is self-closing and the length of the tag.
+fn parse_start_tag(input: &str) -> (String, Vec<(String, String)>, bool, usize) {
+    let mut chars = input.char_indices().peekable();
+    let mut name = String::new();
+    while let Some((_, c)) = chars.next_if(|(_, c)| !c.is_whitespace() && *c != '>' && *c != '/') {
+        name.push(c.to_ascii_lowercase());
+    }
+    let mut attrs = vec![];
+    let mut self_closing = false;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '>' => return (name, attrs, self_closing, i + 1),
+            '/' => self_closing = true,
+            c if c.is_whitespace() => {}
+            c => {
+                self_closing = false;
+                let mut key = String::from(c.to_ascii_lowercase());
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !matches!(c, '=' | '>' | '/'))
+                {
+                    key.push(c.to_ascii_lowercase());
+                }
+                let mut value = String::new();
+                if chars.next_if(|(_, c)| *c == '=').is_some() {
+                    match chars.next_if(|(_, c)| matches!(c, '"' | '\'')) {
+                        Some((_, quote)) => {
+                            for (_, c) in chars.by_ref() {
+                                if c == quote {
+                                    break;
+                                }
+                                value.push(c);
+                            }
+                        }
+                        None => {
+                            while let Some((_, c)) =
+                                chars.next_if(|(_, c)| !c.is_whitespace() && *c != '>')
+                            {
+                                value.push(c);
+                            }
+                        }
+                    }
+                }
+                attrs.push((key, value));
+            }
+        }
+    }
+    (name, attrs, self_closing, input.len())
+}
+
+/// Finds the rules of the stylesheets linked by the page at `path`, in link order.
+fn linked_stylesheets<'a>(
+    html: &'a str,
+    path: &'a Path,
+    sheets: &'a [(PathBuf, Vec<Rule>)],
+) -> impl Iterator<Item = &'a [Rule]> {
+    let base = path.parent().unwrap_or(Path::new(""));
+    html.match_indices('<').filter_map(move |(pos, _)| {
+        let (tag, attrs, _, _) = parse_start_tag(&html[pos + 1..]);
+        if tag != "link" {
+            return None;
+        }
+        let attr = |key: &str| {
+            attrs
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+        };
+        if !attr("rel")?
+            .split_whitespace()
+            .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+        {
+            return None;
+        }
+        let href = attr("href")?.split(['?', '#']).next()?;
+        if href.contains("://") || href.starts_with("//") {
+            return None;
+        }
+        let (_, rules) = match href.strip_prefix('/') {
+            // The site root is unknown, so match by the trailing components.
+            Some(href) => sheets.iter().find(|(sheet, _)| sheet.ends_with(href))?,
+            None => {
+                let target = base.join(href).canonicalize().ok()?;
+                sheets.iter().find(|(sheet, _)| *sheet == target)?
+            }
+        };
+        Some(rules.as_slice())
+    })
+}
+
+/// Collects the text of an HTML document by the family it is rendered with.
+fn attribute_text(html: &str, rules: &[&Rule]) -> HashMap<Option<String>, String> {
+    let mut texts = HashMap::<Option<String>, String>::new();
+    let mut local_rules = vec![];
+    let mut stack = Vec::<Element>::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let in_head = stack.iter().any(|e| e.tag == "head");
+        let text = &rest[..text_end];
+        if !text.trim().is_empty() && !in_head {
+            let family = stack.last().and_then(|e| e.family.clone());
+            texts
+                .entry(family)
+                .or_default()
+                .push_str(&decode::decode_entities(text));
+        }
+        rest = &rest[text_end..];
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, r)| r);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.split_once('>').map_or("", |(_, r)| r);
+        } else if let Some(end_tag) = rest.strip_prefix("</") {
+            let (name, after) = end_tag.split_once('>').unwrap_or((end_tag, ""));
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(pos) = stack.iter().rposition(|e| e.tag == name) {
+                stack.truncate(pos);
+            }
+            rest = after;
+        } else {
+            let (tag, attrs, self_closing, len) = parse_start_tag(&rest[1..]);
+            rest = &rest[1 + len..];
+            if tag.is_empty() {
+                continue;
+            }
+            if matches!(tag.as_str(), "style" | "script") {
+                let close = format!("</{}", tag);
+                let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+                if tag == "style" {
+                    parse_rules(&rest[..end], &mut local_rules);
+                }
+                rest = &rest[end..];
+                rest = rest.split_once('>').map_or("", |(_, r)| r);
+                continue;
+            }
+            let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+            let inherited = stack.last().and_then(|e| e.family.clone());
+            stack.push(Element {
+                tag,
+                id: attr("id").cloned(),
+                classes: attr("class")
+                    .map(|c| c.split_whitespace().map(str::to_owned).collect())
+                    .unwrap_or_default(),
+                family: None,
+            });
+            let best = rules
+                .iter()
+                .copied()
+                .chain(&local_rules)
+                .enumerate()
+                .filter(|(_, rule)| rule.matches(&stack))
+                .max_by_key(|(order, rule)| (rule.specificity, *order));
+            let mut family = match best {
+                Some((_, rule)) => rule.family.clone().or(inherited.clone()),
+                None => inherited.clone(),
+            };
+            if let Some(declared) = attr("style").and_then(|style| declared_family(style)) {
+                family = declared.or(inherited);
+            }
+            let element = stack.last_mut().unwrap();
+            element.family = family;
+            if self_closing || VOID_ELEMENTS.contains(&element.tag.as_str()) {
+                stack.pop();
+            }
+        }
+    }
+    texts
+}
+
+#[test]
+fn test_attribute_text() {
+    let mut rules = vec![];
+    parse_rules(
+        r#"
+        body { font-family: "Text Sans", sans-serif; }
+        h1, .title { font-family: 'Display'; }
+        article > .note { font-family: Mono; }
+        a:hover { font-family: Hover; }
+        @font-face { font-family: Ignored; src: url(x.woff2); }
+        "#,
+        &mut rules,
+    );
+    assert_eq!(rules.len(), 4);
+    let html = r#"<!DOCTYPE html><html><head><title>标题</title>
+        <style>#main .em { font-family: inherit } .em { font-family: Em }</style></head>
+        <body><h1>大字<br>标题</h1><p>正文&amp;<span class="em">斜</span></p>
+        <article id="main"><div class="note">注<span class="em">释</span></div>
+        <p style="font-family: Display">再</p></article><script>"脚本"</script></body></html>"#;
+    let texts = attribute_text(html, &rules.iter().collect::<Vec<_>>())
+        .into_iter()
+        .map(|(family, text)| {
+            let text = text
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+            (family, text)
+        })
+        .collect::<HashMap<_, _>>();
+    assert_eq!(texts[&Some("Display".to_owned())], "大字标题再");
+    assert_eq!(texts[&Some("Text Sans".to_owned())], "正文&");
+    assert_eq!(texts[&Some("Em".to_owned())], "斜");
+    assert_eq!(texts[&Some("Mono".to_owned())], "注释");
+    assert!(!texts.contains_key(&None));
+}
+
+#[test]
+fn test_linked_stylesheets() {
+    let dir = std::env::temp_dir().join(format!("fontchan-cascade-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("css")).unwrap();
+    let mut sheets = vec![];
+    for (name, family) in [("a", "A"), ("b", "B")] {
+        let path = dir.join("css").join(format!("{}.css", name));
+        std::fs::write(&path, format!("p {{ font-family: {} }}", family)).unwrap();
+        let mut rules = vec![];
+        parse_rules(&std::fs::read_to_string(&path).unwrap(), &mut rules);
+        sheets.push((path.canonicalize().unwrap(), rules));
+    }
+    let page = dir.join("index.html");
+    let family = |html: &str| {
+        let linked = linked_stylesheets(html, &page, &sheets)
+            .flatten()
+            .collect::<Vec<_>>();
+        let texts = attribute_text(html, &linked);
+        texts.into_keys().collect::<Vec<_>>()
+    };
+    let html = r#"<link rel="stylesheet" href="css/b.css"><link href="/css/a.css?v=1" rel=stylesheet><p>字"#;
+    assert_eq!(family(html), [Some("A".to_owned())]);
+    let html = r#"<LINK REL="stylesheet" HREF="css/a.css"><link rel="stylesheet" href="./css/b.css"><p>字"#;
+    assert_eq!(family(html), [Some("B".to_owned())]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
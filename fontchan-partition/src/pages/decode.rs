@@ -0,0 +1,122 @@
+use std::borrow::Cow;
+
+use anyhow::{anyhow, Result};
+use encoding_rs::{Encoding, UTF_8};
+
+/// How far into a page to look for `<meta charset>`, following the HTML spec.
+const SNIFF_LIMIT: usize = 1024;
+
+fn sniff_meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(SNIFF_LIMIT)];
+    let head = head.to_ascii_lowercase();
+    let mut rest = head.as_slice();
+    while let Some(pos) = rest.windows(7).position(|w| w == b"charset") {
+        rest = &rest[pos + 7..];
+        let after_ws = rest.trim_ascii_start();
+        let Some(value) = after_ws.strip_prefix(b"=") else {
+            continue;
+        };
+        let value = value.trim_ascii_start();
+        let value = value
+            .strip_prefix(b"\"")
+            .or_else(|| value.strip_prefix(b"'"))
+            .unwrap_or(value);
+        let end = value
+            .iter()
+            .position(|b| !(b.is_ascii_alphanumeric() || b"-_:.".contains(b)))
+            .unwrap_or(value.len());
+        if let Some(encoding) = Encoding::for_label(&value[..end]) {
+            return Some(encoding);
+        }
+    }
+    None
+}
+
+/// Decodes a page by its BOM, `<meta charset>` or the given default encoding.
+pub(crate) fn decode_page<'a>(bytes: &'a [u8], default: &'static Encoding) -> Result<Cow<'a, str>> {
+    let (encoding, bytes) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+        // A <meta> declaring UTF-16 means UTF-8, as per the HTML spec.
+        None => (
+            sniff_meta_charset(bytes)
+                .map(Encoding::output_encoding)
+                .unwrap_or(default),
+            bytes,
+        ),
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .ok_or_else(|| anyhow!("malformed {} content", encoding.name()))
+}
+
+pub(crate) fn parse_encoding(label: Option<&str>) -> Result<&'static Encoding> {
+    match label {
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| anyhow!("unknown encoding: {}", label)),
+        None => Ok(UTF_8),
+    }
+}
+
+/// Decodes the character references of HTML text, keeping unknown ones verbatim.
+pub(crate) fn decode_entities(text: &str) -> Cow<str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('&') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        let decoded = rest[1..].split_once(';').and_then(|(entity, _)| {
+            let ch = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                _ => match entity.strip_prefix('#')? {
+                    hex if hex.starts_with(['x', 'X']) => {
+                        char::from_u32(u32::from_str_radix(&hex[1..], 16).ok()?)?
+                    }
+                    dec => char::from_u32(dec.parse().ok()?)?,
+                },
+            };
+            Some((ch, entity.len() + 2))
+        });
+        match decoded {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+#[test]
+fn test_decode_page() {
+    let (gbk, _, _) = encoding_rs::GBK.encode("<meta charset=\"gbk\"><p>中文</p>");
+    assert_eq!(
+        decode_page(&gbk, UTF_8).unwrap(),
+        "<meta charset=\"gbk\"><p>中文</p>"
+    );
+
+    let (big5, _, _) = encoding_rs::BIG5.encode("<p>中文</p>");
+    assert!(decode_page(&big5, UTF_8).is_err());
+    assert_eq!(
+        decode_page(&big5, encoding_rs::BIG5).unwrap(),
+        "<p>中文</p>"
+    );
+
+    let bom = b"\xef\xbb\xbf<meta charset=gbk>\xe4\xb8\xad";
+    assert_eq!(
+        decode_page(bom, encoding_rs::GBK).unwrap(),
+        "<meta charset=gbk>中"
+    );
+}
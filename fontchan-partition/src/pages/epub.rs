@@ -0,0 +1,266 @@
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::HashMap,
+    io::{Read, Seek},
+    path::Path,
+    sync::OnceLock,
+};
+
+use anyhow::{anyhow, Result};
+use fontchan_util::RoutineArg;
+use quick_xml::events::{BytesStart, Event};
+
+use super::{decode, scan_glob, Page, PagesContext, PagesProvider, ScanCache, TextTransform};
+use crate::config::ChapterWeight;
+
+/// Reads EPUB or zipped HTML archives, taking each chapter as a page.
+///
+/// The chapters are the spine documents of an EPUB, or all the HTML files
+/// of an archive without `META-INF/container.xml`.
+pub struct EpubPagesProvider {
+    cache: OnceLock<Vec<Page>>,
+    glob: Cell<Option<glob::Paths>>,
+    chapter_weight: ChapterWeight,
+    text: TextTransform,
+    scan_cache: Option<ScanCache>,
+}
+
+impl EpubPagesProvider {
+    pub(super) fn new(context: &PagesContext, pattern: &RoutineArg) -> Result<Self> {
+        let pattern = pattern.required()?;
+        Ok(Self {
+            cache: OnceLock::new(),
+            glob: Cell::new(Some(glob::glob(pattern)?)),
+            chapter_weight: context.scan.chapter_weight,
+            text: TextTransform::new(&context.scan),
+            scan_cache: ScanCache::open(context, "epub", &[]),
+        })
+    }
+
+    fn read_book(
+        path: &Path,
+        bytes: &[u8],
+        chapter_weight: ChapterWeight,
+        transform: TextTransform,
+    ) -> Result<Vec<Page>> {
+        let chapters = read_chapters(std::io::Cursor::new(bytes))?;
+        let pages = chapters
+            .into_iter()
+            .map(|(name, text)| {
+                let weight = match chapter_weight {
+                    ChapterWeight::Uniform => 1,
+                    ChapterWeight::Length => text.chars().count().div_ceil(1000).max(1) as u32,
+                };
+                transform
+                    .page(&text)
+                    .with_path(path.join(name))
+                    .with_weight(weight)
+            })
+            .collect();
+        Ok(pages)
+    }
+}
+
+impl PagesProvider for EpubPagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let pages = self.cache.get_or_init(|| {
+            let (chapter_weight, transform) = (self.chapter_weight, self.text);
+            scan_glob(
+                self.glob.take().unwrap(),
+                self.scan_cache.as_ref(),
+                |path, bytes| Self::read_book(path, bytes, chapter_weight, transform),
+            )
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+/// Reads the text of the chapters in reading order, paired with their entry names.
+fn read_chapters<R: Read + Seek>(reader: R) -> Result<Vec<(String, String)>> {
+    let mut archive = zip::ZipArchive::new(reader)?;
+    let names = match read_entry(&mut archive, "META-INF/container.xml") {
+        Ok(container) => {
+            let opf_path = find_attr(&container, b"rootfile", "full-path")?
+                .ok_or_else(|| anyhow!("no rootfile in META-INF/container.xml"))?;
+            let base = opf_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+            spine_hrefs(&read_entry(&mut archive, &opf_path)?)?
+                .iter()
+                .map(|href| resolve_href(base, href))
+                .collect::<Vec<_>>()
+        }
+        Err(_) => {
+            let mut names = archive
+                .file_names()
+                .filter(|name| {
+                    let name = name.to_ascii_lowercase();
+                    name.ends_with(".html") || name.ends_with(".xhtml") || name.ends_with(".htm")
+                })
+                .map(str::to_owned)
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        }
+    };
+    names
+        .into_iter()
+        .map(|name| {
+            let text = extract_text(&read_entry(&mut archive, &name)?)?;
+            Ok((name, text))
+        })
+        .collect()
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, name: &str) -> Result<String> {
+    let mut bytes = vec![];
+    archive
+        .by_name(name)
+        .map_err(|e| anyhow!("{}: {}", name, e))?
+        .read_to_end(&mut bytes)?;
+    Ok(decode::decode_page(&bytes, encoding_rs::UTF_8)?.into_owned())
+}
+
+fn attr(tag: &BytesStart, key: &str) -> Result<Option<String>> {
+    Ok(match tag.try_get_attribute(key)? {
+        Some(attr) => Some(attr.unescape_value()?.into_owned()),
+        None => None,
+    })
+}
+
+/// Finds the attribute of the first element named `name`.
+fn find_attr(xml: &str, name: &[u8], key: &str) -> Result<Option<String>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) | Event::Empty(tag) if tag.local_name().as_ref() == name => {
+                return attr(&tag, key);
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// Lists the hrefs of the spine items of an OPF package document.
+fn spine_hrefs(opf: &str) -> Result<Vec<String>> {
+    let mut reader = quick_xml::Reader::from_str(opf);
+    let mut items = HashMap::new();
+    let mut spine = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) | Event::Empty(tag) => match tag.local_name().as_ref() {
+                b"item" => {
+                    if let (Some(id), Some(href)) = (attr(&tag, "id")?, attr(&tag, "href")?) {
+                        items.insert(id, href);
+                    }
+                }
+                b"itemref" => spine.extend(attr(&tag, "idref")?),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    spine
+        .into_iter()
+        .map(|id| {
+            items
+                .remove(&id)
+                .ok_or_else(|| anyhow!("spine item {} not in manifest", id))
+        })
+        .collect()
+}
+
+/// Resolves an href relative to the directory of the package document.
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut segments = base
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Extracts the text of an XHTML document, leaving out its head, scripts and styles.
+fn extract_text(xhtml: &str) -> Result<String> {
+    let mut reader = quick_xml::Reader::from_str(xhtml);
+    reader.config_mut().check_end_names = false;
+    let mut text = String::new();
+    let mut skipping = 0usize;
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag)
+                if matches!(tag.local_name().as_ref(), b"head" | b"script" | b"style") =>
+            {
+                skipping += 1;
+            }
+            Event::End(tag)
+                if matches!(tag.local_name().as_ref(), b"head" | b"script" | b"style") =>
+            {
+                skipping = skipping.saturating_sub(1);
+            }
+            Event::Text(t) if skipping == 0 => {
+                // Entities of HTML but not XML, e.g., `&nbsp;`, are decoded as well.
+                text.push_str(&decode::decode_entities(&String::from_utf8_lossy(&t)));
+            }
+            Event::CData(data) if skipping == 0 => text.push_str(&data.decode()?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(text)
+}
+
+#[test]
+fn test_read_chapters() -> Result<()> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let mut buf = std::io::Cursor::new(vec![]);
+    let mut writer = zip::ZipWriter::new(&mut buf);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let entries = [
+        (
+            "META-INF/container.xml",
+            r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+        ),
+        (
+            "OEBPS/content.opf",
+            r#"<package><manifest>
+                <item id="c1" href="text/ch1.xhtml"/><item id="c2" href="../ch2.xhtml"/>
+            </manifest><spine><itemref idref="c2"/><itemref idref="c1"/></spine></package>"#,
+        ),
+        (
+            "OEBPS/text/ch1.xhtml",
+            "<html><head><title>目录</title></head><body><p>第一章&nbsp;&amp; &#x5e8f;</p><script>x</script></body></html>",
+        ),
+        ("ch2.xhtml", "<html><body><p>第二章</p></body></html>"),
+    ];
+    for (name, content) in entries {
+        writer.start_file(name, options)?;
+        writer.write_all(content.as_bytes())?;
+    }
+    writer.finish()?;
+
+    let chapters = read_chapters(buf)?;
+    assert_eq!(
+        chapters,
+        [
+            ("ch2.xhtml".to_owned(), "第二章".to_owned()),
+            (
+                "OEBPS/text/ch1.xhtml".to_owned(),
+                "第一章\u{a0}& 序".to_owned()
+            ),
+        ]
+    );
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::{borrow::Cow, cell::Cell, collections::HashMap, path::Path, sync::OnceLock};
+
+use anyhow::{anyhow, bail, Result};
+use fontchan_util::RoutineArg;
+
+use super::{
+    assets::css_literals, decode, scan_glob, Page, PagesContext, PagesProvider, ScanCache,
+};
+use crate::css::strip_comments;
+
+/// Reads the icons used by each page, where a page uses an icon if it
+/// references one of the classes assigned with `.class::before { content: "..." }`.
+///
+/// The routine argument is `<stylesheet>;<pages glob>`.
+pub struct IconPagesProvider {
+    cache: OnceLock<Vec<Page>>,
+    glob: Cell<Option<glob::Paths>>,
+    encoding: &'static encoding_rs::Encoding,
+    classes: HashMap<String, String>,
+    scan_cache: Option<ScanCache>,
+}
+
+impl IconPagesProvider {
+    pub(super) fn new(context: &PagesContext, arg: &RoutineArg) -> Result<Self> {
+        let arg = arg.required()?;
+        let Some((stylesheet, pattern)) = arg.split_once(';') else {
+            bail!("expected `<stylesheet>;<pages glob>`, got {}", arg);
+        };
+        let css = std::fs::read_to_string(stylesheet)
+            .map_err(|e| anyhow!("cannot read {}: {}", stylesheet, e))?;
+        let classes = parse_icon_classes(&css);
+        if classes.is_empty() {
+            bail!("no icon classes found in {}", stylesheet);
+        }
+        Ok(Self {
+            cache: OnceLock::new(),
+            glob: Cell::new(Some(glob::glob(pattern)?)),
+            encoding: decode::parse_encoding(context.scan.encoding.as_deref())?,
+            classes,
+            scan_cache: ScanCache::open(context, "icons", css.as_bytes()),
+        })
+    }
+
+    fn read_page(
+        path: &Path,
+        bytes: &[u8],
+        encoding: &'static encoding_rs::Encoding,
+        classes: &HashMap<String, String>,
+    ) -> Result<Vec<Page>> {
+        let content = decode::decode_page(bytes, encoding)?;
+        let page = class_names(&content)
+            .filter_map(|class| classes.get(class))
+            .flat_map(|s| s.chars())
+            .collect::<Page>();
+        Ok(vec![page.with_path(path.to_owned())])
+    }
+}
+
+impl PagesProvider for IconPagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let pages = self.cache.get_or_init(|| {
+            let (encoding, classes) = (self.encoding, &self.classes);
+            scan_glob(
+                self.glob.take().unwrap(),
+                self.scan_cache.as_ref(),
+                |path, bytes| Self::read_page(path, bytes, encoding, classes),
+            )
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+/// Maps class names to the `content` of their `::before`/`::after` rules.
+fn parse_icon_classes(css: &str) -> HashMap<String, String> {
+    let css = strip_comments(css);
+    let mut classes = HashMap::new();
+    for rule in css.split('}') {
+        let Some((prelude, body)) = rule.rsplit_once('{') else {
+            continue;
+        };
+        // Skip the enclosing at-rules, e.g., `@media ... {`.
+        let selectors = prelude.rsplit('{').next().unwrap_or_default();
+        let Some(content) = body.split(';').find_map(|decl| {
+            let (prop, value) = decl.split_once(':')?;
+            prop.trim().eq_ignore_ascii_case("content").then_some(value)
+        }) else {
+            continue;
+        };
+        let content = css_literals(content).concat();
+        if content.is_empty() {
+            continue;
+        }
+        for selector in selectors.split(',') {
+            let Some((head, _)) = selector
+                .trim()
+                .split_once("::before")
+                .or_else(|| selector.trim().split_once(":before"))
+                .or_else(|| selector.trim().split_once("::after"))
+                .or_else(|| selector.trim().split_once(":after"))
+            else {
+                continue;
+            };
+            let Some((_, class)) = head.rsplit_once('.') else {
+                continue;
+            };
+            if !class.is_empty()
+                && class
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+            {
+                classes.insert(class.to_owned(), content.clone());
+            }
+        }
+    }
+    classes
+}
+
+/// Iterates over the class names in the `class` attributes of the HTML.
+fn class_names(html: &str) -> impl Iterator<Item = &str> {
+    html.match_indices("class=").flat_map(|(i, _)| {
+        let preceded_by_space = html[..i].ends_with(|c: char| c.is_ascii_whitespace());
+        let value = &html[i + "class=".len()..];
+        let value = match value.chars().next() {
+            _ if !preceded_by_space => "",
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                value.split_once(quote).map_or(value, |(v, _)| v)
+            }
+            _ => value
+                .split(|c: char| c.is_ascii_whitespace() || c == '>')
+                .next()
+                .unwrap_or_default(),
+        };
+        value.split_ascii_whitespace()
+    })
+}
+
+#[test]
+fn test_parse_icons() {
+    let css = r#"
+        /* .icon-x::before { content: "\e000" } */
+        .icon-search::before, .icon-find:before { content: "\e001"; }
+        @media print { .btn .icon-close::after { content: '\e002' } }
+        .icon:before { font-family: icons; }
+    "#;
+    let classes = parse_icon_classes(css);
+    assert_eq!(classes.len(), 3);
+    assert_eq!(classes["icon-find"], "\u{e001}");
+    assert_eq!(classes["icon-close"], "\u{e002}");
+
+    let html = r#"<i class="icon icon-search"></i><b data-class="icon-close" class=icon-find>"#;
+    assert_eq!(
+        class_names(html).collect::<Vec<_>>(),
+        ["icon", "icon-search", "icon-find"]
+    );
+}
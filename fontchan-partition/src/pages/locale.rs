@@ -0,0 +1,303 @@
+use std::{borrow::Cow, cell::Cell, path::Path, sync::OnceLock};
+
+use anyhow::{bail, Result};
+use fontchan_util::RoutineArg;
+
+use super::{decode, scan_glob, Page, PagesContext, PagesProvider, ScanCache, TextTransform};
+
+/// Reads UI strings from translation catalogs, i.e., gettext `.po`, Fluent `.ftl`,
+/// i18next `.json`, Android `strings.xml` and iOS `.strings` files.
+pub struct LocalePagesProvider {
+    cache: OnceLock<Vec<Page>>,
+    glob: Cell<Option<glob::Paths>>,
+    per_message: bool,
+    text: TextTransform,
+    scan_cache: Option<ScanCache>,
+}
+
+impl LocalePagesProvider {
+    pub(super) fn new(context: &PagesContext, pattern: &RoutineArg) -> Result<Self> {
+        Self::with_per_message(context, pattern, false)
+    }
+
+    pub(super) fn new_per_message(context: &PagesContext, pattern: &RoutineArg) -> Result<Self> {
+        Self::with_per_message(context, pattern, true)
+    }
+
+    fn with_per_message(
+        context: &PagesContext,
+        pattern: &RoutineArg,
+        per_message: bool,
+    ) -> Result<Self> {
+        let pattern = pattern.required()?;
+        let kind = if per_message {
+            "locale_messages"
+        } else {
+            "locale"
+        };
+        Ok(Self {
+            cache: OnceLock::new(),
+            glob: Cell::new(Some(glob::glob(pattern)?)),
+            per_message,
+            text: TextTransform::new(&context.scan),
+            scan_cache: ScanCache::open(context, kind, &[]),
+        })
+    }
+
+    fn read_file(
+        path: &Path,
+        bytes: &[u8],
+        per_message: bool,
+        text: TextTransform,
+    ) -> Result<Vec<Page>> {
+        let content = decode::decode_page(bytes, encoding_rs::UTF_8)?;
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        let messages = match ext.as_deref() {
+            Some("po" | "pot") => parse_po(&content),
+            Some("ftl") => parse_ftl(&content),
+            Some("json") => parse_i18next(&content)?,
+            Some("xml") => parse_android(&content)?,
+            Some("strings") => parse_apple_strings(&content),
+            _ => bail!("unsupported catalog format"),
+        };
+        let pages = if per_message {
+            messages
+                .iter()
+                .map(|m| text.page(m).with_path(path.to_owned()))
+                .collect()
+        } else {
+            let page = text.page(&messages.join("\n"));
+            vec![page.with_path(path.to_owned())]
+        };
+        Ok(pages)
+    }
+}
+
+impl PagesProvider for LocalePagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let (per_message, text) = (self.per_message, self.text);
+        let pages = self.cache.get_or_init(|| {
+            scan_glob(
+                self.glob.take().unwrap(),
+                self.scan_cache.as_ref(),
+                |path, bytes| Self::read_file(path, bytes, per_message, text),
+            )
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+fn unescape_c(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c @ ('u' | 'U')) => {
+                let hex = chars.clone().take(4).collect::<String>();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(decoded) if hex.len() == 4 => {
+                        out.push(decoded);
+                        chars.nth(3);
+                    }
+                    _ => out.push(c),
+                }
+            }
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Returns the content of the first double-quoted string in `input`, and the rest after it.
+fn quoted(input: &str) -> Option<(&str, &str)> {
+    let start = input.find('"')? + 1;
+    let mut escaped = false;
+    for (i, ch) in input[start..].char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some((&input[start..start + i], &input[start + i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn parse_po(content: &str) -> Vec<String> {
+    let mut messages = vec![];
+    let (mut msgid, mut msgstr) = (String::new(), String::new());
+    let mut in_msgstr = false;
+    let mut flush = |msgid: &mut String, msgstr: &mut String| {
+        // The entry with an empty msgid is the header, whose msgstr holds the metadata.
+        let message = if msgstr.is_empty() { &*msgid } else { &*msgstr };
+        if !msgid.is_empty() {
+            messages.push(unescape_c(message));
+        }
+        msgid.clear();
+        msgstr.clear();
+    };
+    for line in content.lines().map(str::trim) {
+        if line.starts_with("msgid ") {
+            if in_msgstr {
+                flush(&mut msgid, &mut msgstr);
+            }
+            in_msgstr = false;
+        } else if line.starts_with("msgstr") {
+            in_msgstr = true;
+        } else if !line.starts_with('"') {
+            continue;
+        }
+        if let Some((text, _)) = quoted(line) {
+            if in_msgstr {
+                msgstr.push_str(text);
+            } else {
+                msgid.push_str(text);
+            }
+        }
+    }
+    flush(&mut msgid, &mut msgstr);
+    messages
+}
+
+/// Removes `{ ... }` placeables, keeping string literals in them.
+fn strip_placeables(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = "";
+            break;
+        };
+        let mut inner = &rest[start + 1..start + end];
+        while let Some((literal, tail)) = quoted(inner) {
+            out.push_str(&unescape_c(literal));
+            inner = tail;
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn parse_ftl(content: &str) -> Vec<String> {
+    let mut messages = Vec::<String>::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let continued = line.starts_with(char::is_whitespace) && !trimmed.starts_with('.');
+        if continued {
+            if let Some(last) = messages.last_mut() {
+                if !last.is_empty() {
+                    last.push('\n');
+                }
+                last.push_str(&strip_placeables(trimmed));
+                continue;
+            }
+        }
+        if let Some((_, value)) = trimmed.split_once('=') {
+            messages.push(strip_placeables(value.trim()));
+        }
+    }
+    messages.retain(|m| !m.is_empty());
+    messages
+}
+
+fn parse_i18next(content: &str) -> Result<Vec<String>> {
+    fn walk(value: &serde_json::Value, messages: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => {
+                messages.push(strip_placeables(&s.replace("{{", "{").replace("}}", "}")))
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, messages)),
+            serde_json::Value::Object(map) => map.values().for_each(|v| walk(v, messages)),
+            _ => {}
+        }
+    }
+    let mut messages = vec![];
+    walk(&serde_json::from_str(content)?, &mut messages);
+    Ok(messages)
+}
+
+fn parse_android(content: &str) -> Result<Vec<String>> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(content);
+    let mut messages = vec![];
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) if matches!(tag.name().as_ref(), b"string" | b"item") => {
+                current = Some(String::new());
+            }
+            Event::End(tag) if matches!(tag.name().as_ref(), b"string" | b"item") => {
+                if let Some(message) = current.take() {
+                    messages.push(unescape_c(&message));
+                }
+            }
+            Event::Text(text) => {
+                if let Some(message) = &mut current {
+                    message.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(message) = &mut current {
+                    message.push_str(&data.decode()?);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(messages)
+}
+
+fn parse_apple_strings(content: &str) -> Vec<String> {
+    let mut messages = vec![];
+    for line in content.lines() {
+        let Some((_key, rest)) = quoted(line) else {
+            continue;
+        };
+        let Some(rest) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        if let Some((value, _)) = quoted(rest) {
+            messages.push(unescape_c(value));
+        }
+    }
+    messages
+}
+
+#[test]
+fn test_parse_catalogs() {
+    let po = "msgid \"\"\nmsgstr \"Content-Type: text/plain\\n\"\n\n#: a.c\nmsgid \"Hello\"\nmsgstr \"你好\"\n\nmsgid \"Bye\"\nmsgstr \"\"\n\"再\"\n\"见\"\n\nmsgid \"Untranslated\"\nmsgstr \"\"\n";
+    assert_eq!(parse_po(po), ["你好", "再见", "Untranslated"]);
+
+    let ftl = "# comment\nhello = 你好，{ $name }！\nmulti =\n    第一行\n    第二行\n    .title = 标题{ \"「\" }\n";
+    assert_eq!(parse_ftl(ftl), ["你好，！", "第一行\n第二行", "标题「"]);
+
+    let json = r#"{"a": "你好 {{name}}", "nested": {"b": ["一", "二"]}}"#;
+    let mut messages = parse_i18next(json).unwrap();
+    messages.sort();
+    assert_eq!(messages, ["一", "二", "你好 "]);
+
+    let xml = r#"<resources><string name="a">你好 &amp; \'x\'</string><plurals name="p"><item quantity="one">一个</item></plurals><string name="c"><![CDATA[<b>粗</b>]]></string></resources>"#;
+    assert_eq!(
+        parse_android(xml).unwrap(),
+        ["你好 & 'x'", "一个", "<b>粗</b>"]
+    );
+
+    let strings = "/* comment */\n\"greeting\" = \"你好\\\"朋友\\\"\";\n\"u\" = \"\\U4E2D\";\n";
+    assert_eq!(parse_apple_strings(strings), ["你好\"朋友\"", "中"]);
+}
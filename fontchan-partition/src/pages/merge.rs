@@ -0,0 +1,34 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use super::{Page, PagesProvider};
+
+/// Combines the pages of several providers, e.g., of the HTML pages and the locale catalogs.
+pub(crate) struct MergedPagesProvider {
+    inner: Vec<Box<dyn PagesProvider>>,
+    cache: OnceLock<Vec<Page>>,
+}
+
+impl MergedPagesProvider {
+    pub(crate) fn merge(mut inner: Vec<Box<dyn PagesProvider>>) -> Option<Box<dyn PagesProvider>> {
+        match inner.len() {
+            0 => None,
+            1 => inner.pop(),
+            _ => Some(Box::new(Self {
+                inner,
+                cache: OnceLock::new(),
+            })),
+        }
+    }
+}
+
+impl PagesProvider for MergedPagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let pages = self.cache.get_or_init(|| {
+            self.inner
+                .iter()
+                .flat_map(|provider| provider.pages().into_owned())
+                .collect()
+        });
+        Cow::Borrowed(pages)
+    }
+}
@@ -0,0 +1,209 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, OnceLock};
+
+use anyhow::Result;
+use fontchan_util::{autobox, factory, Multi, Registry, RoutineArg};
+
+use crate::config::ScanConfig;
+
+mod assets;
+mod cache;
+mod cascade;
+mod decode;
+mod epub;
+mod icons;
+mod locale;
+mod merge;
+mod transform;
+mod variants;
+
+use assets::AssetsPagesProvider;
+use cache::ScanCache;
+use cascade::CascadePagesProvider;
+use epub::EpubPagesProvider;
+use icons::IconPagesProvider;
+use locale::LocalePagesProvider;
+pub(crate) use merge::MergedPagesProvider;
+use transform::TextTransform;
+pub(crate) use variants::HanVariantsPagesProvider;
+
+#[derive(Debug, Clone)]
+pub struct Page {
+    path: Option<PathBuf>,
+    chars: HashSet<char>,
+    weight: u32,
+    family: Option<String>,
+}
+
+impl Page {
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+    /// Lets the page count as `weight` pages, e.g., for a long chapter.
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+    /// Attributes the text to the font family it is rendered with.
+    pub fn with_family(mut self, family: String) -> Self {
+        self.family = Some(family);
+        self
+    }
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+    pub fn chars(&self) -> &HashSet<char> {
+        &self.chars
+    }
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+    pub fn family(&self) -> Option<&str> {
+        self.family.as_deref()
+    }
+}
+
+impl FromIterator<char> for Page {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        Self {
+            path: None,
+            chars: iter.into_iter().collect(),
+            weight: 1,
+            family: None,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Page {
+    type Item = &'a char;
+    type IntoIter = std::collections::hash_set::Iter<'a, char>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chars.iter()
+    }
+}
+
+pub trait PagesProvider {
+    fn pages(&self) -> Cow<[Page]>;
+}
+autobox!(PagesProvider);
+
+pub struct GlobPagesProvider {
+    cache: OnceLock<Vec<Page>>,
+    glob: Cell<Option<glob::Paths>>,
+    encoding: &'static encoding_rs::Encoding,
+    text: TextTransform,
+    scan_cache: Option<ScanCache>,
+}
+
+impl GlobPagesProvider {
+    fn new(context: &PagesContext, pattern: &RoutineArg) -> Result<Self> {
+        let pattern = pattern.required()?;
+        Ok(Self {
+            cache: OnceLock::new(),
+            glob: Cell::new(Some(glob::glob(&pattern)?)),
+            encoding: decode::parse_encoding(context.scan.encoding.as_deref())?,
+            text: TextTransform::new(&context.scan),
+            scan_cache: ScanCache::open(context, "glob", &[]),
+        })
+    }
+
+    fn read_page(
+        path: &Path,
+        bytes: &[u8],
+        encoding: &'static encoding_rs::Encoding,
+        text: TextTransform,
+    ) -> Result<Vec<Page>> {
+        let content = decode::decode_page(bytes, encoding)?;
+        Ok(vec![text.page(&content).with_path(path.to_owned())])
+    }
+}
+
+impl PagesProvider for GlobPagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let pages = self.cache.get_or_init(|| {
+            let (encoding, text) = (self.encoding, self.text);
+            scan_glob(
+                self.glob.take().unwrap(),
+                self.scan_cache.as_ref(),
+                |path, bytes| Self::read_page(path, bytes, encoding, text),
+            )
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+/// Reads pages from the files in parallel, reporting the files failed to read.
+fn scan_glob<F>(paths: glob::Paths, cache: Option<&ScanCache>, read: F) -> Vec<Page>
+where
+    F: Fn(&Path, &[u8]) -> Result<Vec<Page>> + Sync,
+{
+    use rayon::iter::Either;
+    use rayon::prelude::*;
+
+    let (pages, failures): (Vec<_>, Vec<_>) = paths
+        .par_bridge()
+        .map(|path| {
+            let path = path.map_err(|e| (e.path().to_owned(), e.into()))?;
+            let pages = match cache {
+                Some(cache) => cache.read(&path, &read),
+                None => std::fs::read(&path)
+                    .map_err(Into::into)
+                    .and_then(|bytes| read(&path, &bytes)),
+            };
+            pages.map_err(|e| (path, e))
+        })
+        .partition_map(|r| match r {
+            Ok(pages) => Either::Left(pages),
+            Err(failure) => Either::Right(failure),
+        });
+    report_failures(&failures);
+    if let Some(cache) = cache {
+        if let Err(e) = cache.save() {
+            eprintln!("warning: cannot save {}: {}", cache.path().display(), e);
+        }
+    }
+    pages.into_iter().flatten().collect()
+}
+
+fn report_failures(failures: &[(PathBuf, anyhow::Error)]) {
+    if failures.is_empty() {
+        return;
+    }
+    eprintln!("warning: {} files skipped:", failures.len());
+    for (path, reason) in failures {
+        eprintln!("  {}: {}", path.display(), reason);
+    }
+}
+
+pub(crate) struct PagesContext {
+    pub(crate) scan: ScanConfig,
+}
+
+pub(crate) static PAGES_REGISTRY: LazyLock<Registry<PagesContext, dyn PagesProvider, Multi>> =
+    LazyLock::new(|| {
+        Registry::new()
+            .add("glob", factory!(GlobPagesProvider::new, [context, arg]?))
+            .add(
+                "locale",
+                factory!(LocalePagesProvider::new, [context, arg]?),
+            )
+            .add(
+                "locale_messages",
+                factory!(LocalePagesProvider::new_per_message, [context, arg]?),
+            )
+            .add(
+                "assets",
+                factory!(AssetsPagesProvider::new, [context, arg]?),
+            )
+            .add("icons", factory!(IconPagesProvider::new, [context, arg]?))
+            .add("epub", factory!(EpubPagesProvider::new, [context, arg]?))
+            .add(
+                "cascade",
+                factory!(CascadePagesProvider::new, [context, arg]?),
+            )
+    });
@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use unicode_normalization::UnicodeNormalization;
+
+use super::Page;
+use crate::config::{Normalization, ScanConfig, Variant};
+
+/// Turns the text of a page into the characters the browser draws for it,
+/// which may differ from the characters in the source.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct TextTransform {
+    normalization: Option<Normalization>,
+    case: bool,
+    width: bool,
+}
+
+impl TextTransform {
+    pub(super) fn new(scan: &ScanConfig) -> Self {
+        Self {
+            normalization: scan.normalization,
+            case: scan.variants.contains(&Variant::Case),
+            width: scan.variants.contains(&Variant::Width),
+        }
+    }
+
+    /// Keeps the characters of the text, and adds those of the normalized text
+    /// and the variants.
+    pub(super) fn chars(&self, text: &str) -> HashSet<char> {
+        let mut chars = text.chars().collect::<HashSet<_>>();
+        match self.normalization {
+            Some(Normalization::Nfc) => chars.extend(text.nfc()),
+            Some(Normalization::Nfkc) => chars.extend(text.nfkc()),
+            None => {}
+        }
+        if self.case {
+            let variants = chars
+                .iter()
+                .flat_map(|ch| ch.to_uppercase().chain(ch.to_lowercase()))
+                .collect::<Vec<_>>();
+            chars.extend(variants);
+        }
+        if self.width {
+            let variants = chars
+                .iter()
+                .filter_map(|ch| full_width(*ch))
+                .collect::<Vec<_>>();
+            chars.extend(variants);
+        }
+        chars
+    }
+
+    pub(super) fn page(&self, text: &str) -> Page {
+        self.chars(text).into_iter().collect()
+    }
+}
+
+/// Maps ASCII to the forms of `text-transform: full-width`.
+fn full_width(ch: char) -> Option<char> {
+    match ch {
+        ' ' => Some('\u{3000}'),
+        '!'..='~' => char::from_u32(ch as u32 + 0xfee0),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_text_transform() {
+    let sorted = |transform: TextTransform, text: &str| {
+        let mut chars = transform.chars(text).into_iter().collect::<Vec<_>>();
+        chars.sort();
+        chars.into_iter().collect::<String>()
+    };
+    let transform = TextTransform::default();
+    assert_eq!(sorted(transform, "e\u{301}a"), "ae\u{301}");
+    let transform = TextTransform {
+        normalization: Some(Normalization::Nfc),
+        ..Default::default()
+    };
+    assert_eq!(sorted(transform, "e\u{301}"), "eé\u{301}");
+    let transform = TextTransform {
+        normalization: Some(Normalization::Nfkc),
+        case: true,
+        width: true,
+    };
+    assert_eq!(sorted(transform, "ﬁß"), "FISfiß\u{fb01}ＦＩＳｆｉ");
+}
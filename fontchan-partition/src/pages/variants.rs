@@ -0,0 +1,61 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use super::{Page, PagesProvider};
+
+include!("../../variant-preset/han_variants.rs");
+
+/// Expands the characters of the pages with their Simplified/Traditional and
+/// semantic variants from Unihan, for pages converted between the scripts client-side.
+pub(crate) struct HanVariantsPagesProvider {
+    inner: Box<dyn PagesProvider>,
+    cache: OnceLock<Vec<Page>>,
+}
+
+impl HanVariantsPagesProvider {
+    pub(crate) fn new(inner: Box<dyn PagesProvider>) -> Self {
+        Self {
+            inner,
+            cache: OnceLock::new(),
+        }
+    }
+}
+
+impl PagesProvider for HanVariantsPagesProvider {
+    fn pages(&self) -> Cow<[Page]> {
+        let pages = self.cache.get_or_init(|| {
+            self.inner
+                .pages()
+                .iter()
+                .map(|page| expand(page, HAN_VARIANTS))
+                .collect()
+        });
+        Cow::Borrowed(pages)
+    }
+}
+
+/// Expands the page with the variants in `table`, a list of pairs sorted by the first.
+fn expand(page: &Page, table: &[(char, char)]) -> Page {
+    let mut page = page.clone();
+    let variants = page
+        .chars
+        .iter()
+        .flat_map(|&ch| {
+            let start = table.partition_point(|(from, _)| *from < ch);
+            table[start..]
+                .iter()
+                .take_while(move |(from, _)| *from == ch)
+                .map(|(_, to)| *to)
+        })
+        .collect::<Vec<_>>();
+    page.chars.extend(variants);
+    page
+}
+
+#[test]
+fn test_expand() {
+    let table = [('书', '書'), ('发', '發'), ('发', '髮'), ('書', '书')];
+    let page = "书发a".chars().collect::<Page>();
+    let mut chars = expand(&page, &table).chars.into_iter().collect::<Vec<_>>();
+    chars.sort();
+    assert_eq!(chars.into_iter().collect::<String>(), "a书发書發髮");
+}
@@ -0,0 +1,162 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Write},
+};
+
+use fontchan_unicode::URange;
+
+const SAMPLE_SIZE: usize = 32;
+
+/// The chars that every partition must assign to some chunk.
+pub(crate) enum Coverage<'a> {
+    CharBase(Cow<'a, HashSet<char>>),
+    Pages(HashSet<char>),
+}
+
+impl Coverage<'_> {
+    fn chars(&self) -> &HashSet<char> {
+        match self {
+            Coverage::CharBase(chars) => chars,
+            Coverage::Pages(chars) => chars,
+        }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Coverage::CharBase(_) => "char base",
+            Coverage::Pages(_) => "pages",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    missing: Vec<char>,
+    missing_from: &'static str,
+    duplicated: Vec<(char, Vec<usize>)>,
+    empty: Vec<usize>,
+    oversized: Vec<(usize, usize)>,
+    limit: Option<usize>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.duplicated.is_empty()
+            && self.empty.is_empty()
+            && self.oversized.is_empty()
+    }
+}
+
+impl Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid partition:")?;
+        if !self.missing.is_empty() {
+            let sample = self.missing.iter().take(SAMPLE_SIZE).collect::<String>();
+            writeln!(
+                f,
+                "  {} chars of {} are not assigned to any chunk: {:?}{}",
+                self.missing.len(),
+                self.missing_from,
+                sample,
+                if self.missing.len() > SAMPLE_SIZE {
+                    "..."
+                } else {
+                    ""
+                }
+            )?;
+        }
+        if !self.duplicated.is_empty() {
+            let mut sample = String::new();
+            for (ch, chunks) in self.duplicated.iter().take(SAMPLE_SIZE) {
+                write!(sample, " {:?} {:?}", ch, chunks)?;
+            }
+            writeln!(
+                f,
+                "  {} chars are assigned to multiple chunks:{}",
+                self.duplicated.len(),
+                sample
+            )?;
+        }
+        for idx in &self.empty {
+            writeln!(f, "  chunk #{} is empty", idx)?;
+        }
+        for (idx, size) in &self.oversized {
+            writeln!(
+                f,
+                "  chunk #{} has {} chars, exceeding the limit of {}",
+                idx,
+                size,
+                self.limit.unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn validate(
+    ranges: &[URange],
+    coverage: Option<&Coverage>,
+    limit: Option<usize>,
+) -> ValidationReport {
+    let mut report = ValidationReport {
+        limit,
+        ..Default::default()
+    };
+    let mut owners = HashMap::<char, Vec<usize>>::new();
+    for (idx, range) in ranges.iter().enumerate() {
+        let mut size = 0;
+        for ch in range.as_chars() {
+            owners.entry(ch).or_default().push(idx);
+            size += 1;
+        }
+        if size == 0 {
+            report.empty.push(idx);
+        }
+        if limit.is_some_and(|limit| size > limit) {
+            report.oversized.push((idx, size));
+        }
+    }
+    if let Some(coverage) = coverage {
+        report.missing_from = coverage.name();
+        report.missing = coverage
+            .chars()
+            .iter()
+            .filter(|ch| !owners.contains_key(ch))
+            .cloned()
+            .collect();
+        report.missing.sort_unstable();
+    }
+    report.duplicated = owners
+        .into_iter()
+        .filter(|(_, chunks)| chunks.len() > 1)
+        .collect();
+    report.duplicated.sort_unstable();
+    report
+}
+
+#[test]
+fn test_validate() {
+    use fontchan_unicode::URangeBuilder;
+
+    let ranges = ["abc", "cd", "", "efghij"]
+        .map(|s| URangeBuilder::from_chars(s.chars()).build())
+        .to_vec();
+    let char_base = Coverage::CharBase(Cow::Owned("abcdefghijxy".chars().collect()));
+    let report = validate(&ranges, Some(&char_base), Some(4));
+    assert!(!report.is_ok());
+    assert_eq!(report.missing, ['x', 'y']);
+    assert_eq!(report.duplicated, [('c', vec![0, 1])]);
+    assert_eq!(report.empty, [2]);
+    assert_eq!(report.oversized, [(3, 6)]);
+
+    let char_base = Coverage::CharBase(Cow::Owned("abd".chars().collect()));
+    let report = validate(&ranges[..2], Some(&char_base), None);
+    assert_eq!(report.duplicated.len(), 1);
+    assert!(report.missing.is_empty());
+
+    let pages = Coverage::Pages("abz".chars().collect());
+    let report = validate(&ranges[..1], Some(&pages), None);
+    assert_eq!(report.missing, ['z']);
+    assert!(report.to_string().contains("1 chars of pages"));
+}
@@ -0,0 +1,50 @@
+import os.path as osp
+import urllib.request
+import zipfile
+
+pwd = osp.dirname(osp.abspath(__file__))
+unihan_zip = osp.join(pwd, "Unihan.zip")
+# A versioned release, so the content is fixed.
+url = "https://www.unicode.org/Public/16.0.0/ucd/Unihan.zip"
+fields = {"kSimplifiedVariant", "kTraditionalVariant", "kSemanticVariant"}
+
+
+def download_with_progress(url, output_path):
+    def reporthook(block_num, block_size, total_size):
+        downloaded = block_num * block_size
+        if total_size > 0:
+            progress = downloaded / total_size * 100
+            print(f"\rDownloading: {progress:.2f}%", end="")
+        else:
+            print(f"\rDownloaded {downloaded} bytes", end="")
+
+    urllib.request.urlretrieve(url, output_path, reporthook)
+    print("\nDownload complete.")
+
+
+if not osp.exists(unihan_zip):
+    download_with_progress(url, unihan_zip)
+
+with zipfile.ZipFile(unihan_zip) as zf:
+    variants = zf.read("Unihan_Variants.txt").decode("utf-8")
+
+# Variants are made symmetric, e.g., U+4E66 书 and U+66F8 書 map to each other.
+pairs = set()
+for line in variants.splitlines():
+    if line.startswith("#") or not line.strip():
+        continue
+    code, field, values = line.split("\t", 2)
+    if field not in fields:
+        continue
+    a = int(code[2:], 16)
+    for value in values.split():
+        # Semantic variants may be annotated with their sources, e.g., `U+5B78<kMatthews`.
+        b = int(value.split("<")[0][2:], 16)
+        if a != b:
+            pairs.add((a, b))
+            pairs.add((b, a))
+
+with open(osp.join(pwd, "han_variants.rs"), "w", encoding="utf-8") as f:
+    f.write("const HAN_VARIANTS: &'static [(char, char)] = &[")
+    f.write(",\n".join(f"('\\u{{{a:X}}}', '\\u{{{b:X}}}')" for a, b in sorted(pairs)))
+    f.write("];")
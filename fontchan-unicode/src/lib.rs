@@ -130,6 +130,22 @@ impl UpdateInto for &'_ URange {
     }
 }
 
+impl std::fmt::Display for URange {
+    /// Formats the range in CSS `unicode-range` syntax, e.g., `U+41,U+4e00-4e05`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, span) in self.spans.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "U+{:x}", span.start as u32)?;
+            if !span.is_single() {
+                write!(f, "-{:x}", span.end as u32)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl AsRef<[USpan]> for URange {
     fn as_ref(&self) -> &[USpan] {
         &self.spans
@@ -223,6 +239,20 @@ fn test_unicode_range_from_css_syntax() {
     dbg!(set.build());
 }
 
+#[test]
+fn test_unicode_range_css_syntax_roundtrip() {
+    let range = URangeBuilder::from_css_syntax("U+4e00-4e05, U+41, U+3??")
+        .unwrap()
+        .build();
+    assert_eq!(range.to_string(), "U+41,U+300-3ff,U+4e00-4e05");
+    assert_eq!(
+        URangeBuilder::from_css_syntax(range.to_string())
+            .unwrap()
+            .build(),
+        range
+    );
+}
+
 #[derive(Debug)]
 pub struct UName<'a>(Cow<'a, str>);
 
@@ -1,4 +1,7 @@
-use std::{ffi::OsStr, path::PathBuf};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{bail, Result};
 use tempfile::{NamedTempFile, TempPath};
@@ -13,9 +16,12 @@ impl AtomicPath {
         Self { real }
     }
     pub fn into_writable(self) -> Result<WritableAtomicPath> {
-        let Some(parent_dir) = self.real.parent() else {
+        let Some(mut parent_dir) = self.real.parent() else {
             bail!("Parent directory not found for path: {:?}", self.real);
         };
+        if parent_dir == Path::new("") {
+            parent_dir = Path::new(".");
+        }
         let temp_file = NamedTempFile::with_prefix_in("__fontchan", parent_dir)?;
         Ok(WritableAtomicPath {
             real: self.real,
@@ -79,9 +79,16 @@ impl<C: 'static, T: ?Sized, Q: Quant> Registry<C, T, Q> {
         builder.0(context, &routine.arg).map_err(|e| anyhow!("{}: {}", routine, e))
     }
 
+    /// Returns the routine that [`Registry::build`] would use, falling back to the default.
+    pub fn resolve<'a>(
+        &'a self,
+        routine: &'a Option<Con<Routine, Q>>,
+    ) -> Option<&'a Con<Routine, Q>> {
+        routine.as_ref().or(self.default.as_ref())
+    }
+
     pub fn build(&self, context: &C, routine: &Option<Con<Routine, Q>>) -> Result<Con<Box<T>, Q>> {
-        let routine = routine.as_ref();
-        let Some(r) = routine.or(self.default.as_ref()) else {
+        let Some(r) = self.resolve(routine) else {
             return Con::missing().ok_or_else(|| anyhow!("routine required"));
         };
         r.map_ref(|r| self.build_one(context, r)).collect_result()
@@ -13,6 +13,8 @@ pub trait Functor: UnaryTy {
     fn map_ref<'a, T: 'a, U, F>(value: &'a Self::Out<T>, f: F) -> Self::Out<U>
     where
         F: FnMut(&'a T) -> U;
+    /// Collects `values`, or returns `None` if `Self` cannot hold that many of them.
+    fn from_vec<T>(values: Vec<T>) -> Option<Self::Out<T>>;
 }
 
 pub struct VecTy;
@@ -39,6 +41,10 @@ impl Functor for VecTy {
     {
         value.iter().map(f).collect()
     }
+
+    fn from_vec<T>(values: Vec<T>) -> Option<Self::Out<T>> {
+        Some(values)
+    }
 }
 
 pub struct OptionTy;
@@ -66,6 +72,9 @@ impl Functor for OptionTy {
     {
         value.as_ref().map(f)
     }
+    fn from_vec<T>(mut values: Vec<T>) -> Option<Self::Out<T>> {
+        (values.len() <= 1).then(|| values.pop())
+    }
 }
 
 pub struct IdTy;
@@ -90,6 +99,9 @@ impl Functor for IdTy {
     {
         f(value)
     }
+    fn from_vec<T>(mut values: Vec<T>) -> Option<Self::Out<T>> {
+        (values.len() == 1).then(|| values.pop()).flatten()
+    }
 }
 
 pub trait FunctorResultExt: UnaryTy {
@@ -2,11 +2,13 @@ use core::str;
 use std::{
     borrow::Cow,
     fmt::{Debug, Write},
+    marker::PhantomData,
     ops::Deref,
 };
 
 use crate::hkt::*;
 use crate::quant::*;
+use crate::{Hasher, UpdateInto};
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
 
@@ -81,15 +83,52 @@ impl std::fmt::Display for Routine {
     }
 }
 
+impl UpdateInto for &Routine {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        hasher.update(self.to_string().as_bytes());
+    }
+}
+
+impl<Q: Quant> UpdateInto for &Con<Routine, Q> {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        self.map_ref(|routine| routine.update_into(hasher))
+            .collect();
+    }
+}
+
+/// Accepts a routine, or a list of them where `Q` holds several.
 impl<'de, Q: Quant> Deserialize<'de> for Con<Routine, Q> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let input = CowStr::deserialize(deserializer)?;
-        Routine::new::<Q>(input)
-            .map(Con)
-            .map_err(serde::de::Error::custom)
+        use serde::de::{Error, SeqAccess, Visitor};
+
+        struct RoutineVisitor<Q>(PhantomData<Q>);
+
+        impl<'de, Q: Quant> Visitor<'de> for RoutineVisitor<Q> {
+            type Value = Con<Routine, Q>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a routine or a list of routines")
+            }
+            fn visit_str<E: Error>(self, input: &str) -> Result<Self::Value, E> {
+                Routine::new::<Q>(input.to_owned())
+                    .map(Con)
+                    .map_err(E::custom)
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut routines = vec![];
+                while let Some(input) = seq.next_element::<CowStr>()? {
+                    routines.push(Routine::new::<Req>(input).map_err(A::Error::custom)?);
+                }
+                Q::M::from_vec(routines)
+                    .map(Con)
+                    .ok_or_else(|| A::Error::custom("expect a single routine"))
+            }
+        }
+
+        deserializer.deserialize_any(RoutineVisitor(PhantomData))
     }
 }
 
@@ -1,4 +1,7 @@
 fn main() {
+    if std::env::var_os("CARGO_FEATURE_HARFBUZZ").is_none() {
+        return;
+    }
     let libpath = std::env::var("LIBPATH").unwrap_or(String::new());
     println!("cargo:rustc-link-search={}", libpath);
     println!("cargo:rustc-link-lib=zlib")
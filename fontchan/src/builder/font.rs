@@ -5,16 +5,22 @@ use std::{
     fs,
     os::windows::fs::FileTypeExt,
     path::{Path, PathBuf},
-    sync::{Arc, LazyLock},
+    sync::{Arc, LazyLock, Mutex, OnceLock},
+    time::Duration,
 };
 
+use allsorts::tables::FontTableProvider;
 use anyhow::{anyhow, bail, Result};
 use fontchan_unicode::{UEntry, UName};
+#[cfg(feature = "harfbuzz")]
+use harfbuzz_rs_now::{Face, Shared};
 use rayon::prelude::*;
 
 use super::tmpl::{PathTmpl, Tmpl, TmplParams};
 use crate::{
-    config::{Config, FontConfig},
+    config::{
+        AllsortsConfig, AxisPin, CommandConfig, Config, FontBuilderConfig, FontConfig, FontFormat,
+    },
     paramdef,
 };
 use fontchan_util::{
@@ -22,8 +28,19 @@ use fontchan_util::{
     Registry, Req, UpdateInto, WritableAtomicPath,
 };
 
+#[cfg(feature = "harfbuzz")]
+mod harfbuzz;
+#[cfg(feature = "harfbuzz")]
+use harfbuzz::HarfbuzzBackend;
+
 pub type FontOutputTmpl = PathTmpl<'static, FontOutputTmplParams<'static>>;
-paramdef!(pub, FontOutputTmplParams, font_out_params, fid = "<FID>");
+paramdef!(
+    pub,
+    FontOutputTmplParams,
+    font_out_params,
+    fid = "<FID>",
+    ext = "<EXT>"
+);
 
 pub trait FontProvider: Sync {
     fn file(&self) -> &LazyFile;
@@ -55,47 +72,129 @@ struct DestInfo {
     tmpl: Arc<FontOutputTmpl>,
     fid: Fid<'static>,
     digest: DigestString,
+    /// Path of the first format, written by the backend.
     file_path: PathBuf,
+    /// Paths of the other formats, converted from `file_path`.
+    other_paths: Vec<PathBuf>,
 }
 
 impl DestInfo {
-    fn new<'a>(tmpl: Arc<FontOutputTmpl>, name: UName<'a>, digest: DigestString) -> Self {
+    fn new<'a>(
+        tmpl: Arc<FontOutputTmpl>,
+        formats: &[FontFormat],
+        name: UName<'a>,
+        digest: DigestString,
+    ) -> Self {
         let fid = Fid::new(&name, &digest);
-        let file_path = tmpl
-            .render(&font_out_params!(fid = fid.as_str()))
-            .into_owned();
+        let mut paths = formats
+            .iter()
+            .map(|format| format.ext())
+            .chain(formats.is_empty().then_some(""))
+            .map(|ext| {
+                tmpl.render(&font_out_params!(fid = fid.as_str(), ext = ext))
+                    .into_owned()
+            });
+        let file_path = paths.next().unwrap();
         Self {
+            other_paths: paths.collect(),
             tmpl,
             digest,
             fid,
             file_path,
         }
     }
+    fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.file_path).chain(&self.other_paths)
+    }
     fn as_writable(&mut self) -> Result<TmpFileGuard> {
         let tmp = AtomicPath::from(&self.file_path).into_writable()?;
         Ok(TmpFileGuard(self, tmp))
     }
     fn changed(&self) -> Result<bool> {
-        Ok(!std::fs::exists(&self.file_path)?)
+        for path in self.paths() {
+            if !std::fs::exists(path)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+    /// Writes the other formats from the subset at `file_path`.
+    fn convert(&self) -> Result<()> {
+        if self.other_paths.is_empty() {
+            return Ok(());
+        }
+        let data = fs::read(&self.file_path)?;
+        let font = smart_load_font(&data)?;
+        for path in &self.other_paths {
+            let binary = smart_save_font(Cow::Borrowed(&font), path)?;
+            let dest = AtomicPath::from(path).into_writable()?;
+            fs::write(dest.as_ref(), binary)?;
+            dest.commit()?;
+        }
+        Ok(())
     }
 }
 
 pub struct Context {
     pub source: Box<dyn FontProvider>,
+    pub face_index: u32,
+    pub axes: Vec<AxisPin>,
+    pub formats: Vec<FontFormat>,
     pub dest_tmpl: Arc<FontOutputTmpl>,
+    source_cache: Mutex<Option<Arc<SourceCache>>>,
+}
+
+/// The source font decoded once and shared by the chunks of a [`Context`].
+#[derive(Default)]
+struct SourceCache {
+    sfnt: OnceLock<Result<Arc<[u8]>, String>>,
+    #[cfg(feature = "harfbuzz")]
+    face: OnceLock<Shared<Face<'static>>>,
 }
 
 impl Context {
+    fn source_cache(&self) -> Arc<SourceCache> {
+        let mut cache = self.source_cache.lock().unwrap();
+        cache.get_or_insert_with(Default::default).clone()
+    }
+    /// Drops the decoded source once no chunk of the font is being built.
+    fn release_source_cache(&self) {
+        self.source_cache.lock().unwrap().take();
+    }
+    /// The source decoded from WOFF or WOFF2.
+    fn sfnt(&self) -> Result<Arc<[u8]>> {
+        let cache = self.source_cache();
+        let sfnt = cache.sfnt.get_or_init(|| {
+            let file_bytes = self
+                .source
+                .file()
+                .content()
+                .map_err(|reason| format!("fail to open file: {:?}", reason))?;
+            let font_bytes = smart_load_font(file_bytes).map_err(|e| e.to_string())?;
+            Ok(Arc::from(font_bytes))
+        });
+        sfnt.clone().map_err(|e| anyhow!(e))
+    }
     fn get_hash<'a>(&self, backend: &dyn Backend, entry: &UEntry<'a>) -> DigestString {
-        Digester::new()
+        let mut digester = Digester::new()
             .push(self.source.file())
+            .push(self.face_index.to_le_bytes());
+        for axis in &self.axes {
+            digester = digester.push_dyn(axis);
+        }
+        digester
             .push(entry.range)
             .push_dyn(backend.characteristics())
             .base64_result()
     }
     fn dest_info<'a>(&self, backend: &dyn Backend, entry: &UEntry<'a>) -> DestInfo {
         let digest = self.get_hash(backend, entry);
-        DestInfo::new(self.dest_tmpl.clone(), entry.name.clone_s(), digest)
+        DestInfo::new(
+            self.dest_tmpl.clone(),
+            &self.formats,
+            entry.name.clone_s(),
+            digest,
+        )
     }
 }
 
@@ -103,7 +202,11 @@ impl From<&FontConfig> for Context {
     fn from(spec: &FontConfig) -> Self {
         Self {
             source: Box::new(spec.input_path.clone()),
+            face_index: spec.face_index,
+            axes: spec.axes.clone(),
+            formats: spec.formats.clone(),
             dest_tmpl: spec.output_tmpl.clone(),
+            source_cache: Mutex::new(None),
         }
     }
 }
@@ -111,56 +214,222 @@ impl From<&FontConfig> for Context {
 pub struct Builder {
     contexts: Vec<Context>,
     backend: Box<dyn Backend>,
+    keep_going: bool,
 }
 
 impl Builder {
     pub fn new(config: &Config) -> Result<Self> {
+        let backend = BACKEND_REGISTRY
+            .build(&config.builder.font, &config.builder.font.backend)?
+            .into_data();
+        if !backend.instances() && config.fonts.iter().any(|f| !f.axes.is_empty()) {
+            bail!("axes of variable fonts require the harfbuzz backend");
+        }
+        for font in &config.fonts {
+            let has_ext = font.output_tmpl.name_template.as_str().contains("<EXT>");
+            if has_ext == font.formats.is_empty() {
+                bail!(
+                    "output_tmpl {:?} must contain <EXT> if and only if formats are given",
+                    font.output_tmpl.name_template.as_str()
+                );
+            }
+            if font.css.src.contains("<EXT>") == font.formats.is_empty() {
+                bail!(
+                    "css.src {:?} must contain <EXT> if and only if formats are given",
+                    font.css.src
+                );
+            }
+        }
         Ok(Self {
             contexts: config.fonts.iter().map(Into::into).collect(),
-            backend: BACKEND_REGISTRY
-                .build(&(), &config.builder.font.backend)?
-                .into_data(),
+            backend,
+            keep_going: false,
         })
     }
 
+    /// Builds all the chunks despite failures, which are then reported together as [`BuildFailures`].
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Sizes of the already built subsets for each entry, summed over all fonts.
+    /// `None` if any font of the entry has not been built yet.
+    pub fn built_sizes(&self, entries: &[UEntry]) -> Vec<Option<u64>> {
+        entries
+            .iter()
+            .map(|entry| {
+                self.contexts
+                    .iter()
+                    .map(|ctx| {
+                        let dest_info = ctx.dest_info(&*self.backend, entry);
+                        fs::metadata(&dest_info.file_path).ok().map(|m| m.len())
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
     pub fn build<'a>(
         &self,
         entries: impl IntoParallelIterator<Item = UEntry<'a>>,
     ) -> Result<BuildResults<'a>> {
-        let contexts = &self.contexts;
+        self.build_contexts(&self.contexts, entries)
+    }
+
+    /// Builds the entries for the font at `idx` only.
+    pub fn build_font<'a>(
+        &self,
+        idx: usize,
+        entries: impl IntoParallelIterator<Item = UEntry<'a>>,
+    ) -> Result<BuildResults<'a>> {
+        self.build_contexts(&self.contexts[idx..=idx], entries)
+    }
+
+    fn build_contexts<'a>(
+        &self,
+        contexts: &[Context],
+        entries: impl IntoParallelIterator<Item = UEntry<'a>>,
+    ) -> Result<BuildResults<'a>> {
         for ctx in contexts {
             fs::create_dir_all(&ctx.dest_tmpl.directory)?;
         }
-        let his = History::new(contexts)?;
-        let history = &his;
-        entries
-            .into_par_iter()
-            .flat_map(|entry| {
-                let entry = Arc::new(entry);
-                contexts.par_iter().map(move |ctx| {
-                    let mut dest_info = ctx.dest_info(&*self.backend, &entry);
-                    if let Some(old_files) = history.query(&dest_info) {
-                        for old in old_files {
-                            if old.as_path() != dest_info.file_path {
-                                let _ = std::fs::remove_file(&old)?;
-                            }
-                        }
-                    }
-                    if dest_info.changed()? {
-                        self.backend
-                            .do_subset(&ctx, &mut dest_info, entry.clone())?;
+        let history = History::new(contexts)?;
+        let entries = entries.into_par_iter().map(Arc::new).collect::<Vec<_>>();
+        // Fonts are built one by one, so that only one decoded source is held at a time.
+        let mut ctx_results = Vec::with_capacity(contexts.len());
+        let mut failures = BuildFailures::default();
+        for ctx in contexts {
+            let results = entries
+                .par_iter()
+                .map(|entry| self.build_entry(ctx, &history, entry));
+            let results = if self.keep_going {
+                let mut built = vec![];
+                for result in results.collect::<Vec<_>>() {
+                    match result {
+                        Ok(result) => built.push(result),
+                        Err(error) => failures.errors.push(error),
                     }
-                    Ok(BuildResult {
-                        fid: dest_info.fid,
-                        digest: dest_info.digest,
-                    })
-                })
-            })
-            .collect::<Result<Vec<_>>>()
-            .map(BuildResults::new_with(contexts.len()))
+                }
+                Ok(built)
+            } else {
+                results.collect::<Result<Vec<_>, _>>()
+            };
+            ctx.release_source_cache();
+            ctx_results.push(results?.into_iter());
+        }
+        if !failures.errors.is_empty() {
+            failures.chunks = entries.len() * contexts.len();
+            return Err(failures.into());
+        }
+        let mut results = Vec::with_capacity(entries.len() * contexts.len());
+        for _ in 0..entries.len() {
+            results.extend(ctx_results.iter_mut().flat_map(Iterator::next));
+        }
+        Ok(BuildResults::new_with(contexts.len())(results))
+    }
+
+    fn build_entry<'a>(
+        &self,
+        ctx: &Context,
+        history: &History,
+        entry: &Arc<UEntry<'a>>,
+    ) -> Result<BuildResult<'a>, ChunkError> {
+        let fail = |stage| {
+            move |error| ChunkError {
+                font: ctx.source.file().path().to_owned(),
+                entry: entry.name.as_ref().to_owned(),
+                stage,
+                error,
+            }
+        };
+        let mut dest_info = ctx.dest_info(&*self.backend, entry);
+        if let Some(old_files) = history.query(&dest_info) {
+            for old in old_files {
+                if !dest_info.paths().any(|path| path == old) {
+                    std::fs::remove_file(old).map_err(|e| fail("remove stale files")(e.into()))?;
+                }
+            }
+        }
+        if dest_info.changed().map_err(fail("check outputs"))? {
+            self.backend
+                .do_subset(ctx, &mut dest_info, entry.clone())
+                .map_err(fail("subset"))?;
+            dest_info.convert().map_err(fail("convert formats"))?;
+        }
+        Ok(BuildResult {
+            fid: dest_info.fid,
+            digest: dest_info.digest,
+            file_path: dest_info.file_path,
+        })
+    }
+}
+
+/// Failure of building a chunk of a font.
+#[derive(Debug)]
+pub struct ChunkError {
+    pub font: PathBuf,
+    pub entry: String,
+    pub stage: &'static str,
+    pub error: anyhow::Error,
+}
+
+impl std::fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot {} chunk {} of font {}: {}",
+            self.stage,
+            self.entry,
+            self.font.display(),
+            self.error
+        )
     }
 }
 
+impl std::error::Error for ChunkError {}
+
+/// Failures of the chunks in a keep-going build.
+#[derive(Debug, Default)]
+pub struct BuildFailures {
+    pub chunks: usize,
+    pub errors: Vec<ChunkError>,
+}
+
+impl BuildFailures {
+    /// Gathers the failures of several builds, or returns the first error of other kinds.
+    pub fn merge<T>(results: impl IntoIterator<Item = Result<T>>) -> Result<Vec<T>> {
+        let mut values = vec![];
+        let mut merged = BuildFailures::default();
+        for result in results {
+            match result.map_err(|e| e.downcast::<BuildFailures>()) {
+                Ok(value) => values.push(value),
+                Err(Ok(failures)) => {
+                    merged.chunks += failures.chunks;
+                    merged.errors.extend(failures.errors);
+                }
+                Err(Err(error)) => return Err(error),
+            }
+        }
+        if !merged.errors.is_empty() {
+            return Err(merged.into());
+        }
+        Ok(values)
+    }
+}
+
+impl std::fmt::Display for BuildFailures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} of {} chunks failed:", self.errors.len(), self.chunks)?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BuildFailures {}
+
 #[derive(Debug)]
 pub struct Fid<'a>(Cow<'a, str>);
 
@@ -177,6 +446,7 @@ pub struct BuildResult<'a> {
     pub fid: Fid<'a>,
     #[allow(unused)]
     pub digest: DigestString,
+    pub file_path: PathBuf,
 }
 
 /// Results are arranged as entry-major, context-minor order, i.e.,
@@ -190,6 +460,12 @@ impl<'a> BuildResults<'a> {
     fn new_with(ctx_count: usize) -> impl FnOnce(Vec<BuildResult<'a>>) -> Self {
         move |results| Self { ctx_count, results }
     }
+    pub fn ctx_count(&self) -> usize {
+        self.ctx_count
+    }
+    pub fn get(&self, entry: usize, ctx: usize) -> &BuildResult<'a> {
+        &self.results[entry * self.ctx_count + ctx]
+    }
     pub fn entry_minor_iter(&self) -> impl Iterator<Item = &BuildResult<'a>> {
         let entry_count = self.results.len() / self.ctx_count;
         (0..self.ctx_count).flat_map(move |i| {
@@ -202,12 +478,20 @@ impl<'a> BuildResults<'a> {
     }
 }
 
-static BACKEND_REGISTRY: LazyLock<Registry<(), dyn Backend, Req>> = LazyLock::new(|| {
-    Registry::new()
-        .add("pyft", factory!(PyftBackend))
-        .add("harfbuzz", factory!(HarfbuzzBackend))
-        .with_default(routine!("harfbuzz"))
-});
+static BACKEND_REGISTRY: LazyLock<Registry<FontBuilderConfig, dyn Backend, Req>> =
+    LazyLock::new(|| {
+        let registry = Registry::new()
+            .add("pyft", factory!(CommandBackend::pyftsubset, []))
+            .add("command", factory!(CommandBackend::new, [context]?))
+            .add("allsorts", factory!(AllsortsBackend::new, [context]?));
+        #[cfg(feature = "harfbuzz")]
+        let registry = registry
+            .add("harfbuzz", factory!(HarfbuzzBackend::new, [context]?))
+            .with_default(routine!("harfbuzz"));
+        #[cfg(not(feature = "harfbuzz"))]
+        let registry = registry.with_default(routine!("allsorts"));
+        registry
+    });
 
 trait Backend: Sync {
     fn do_subset<'a>(
@@ -217,20 +501,14 @@ trait Backend: Sync {
         entry: Arc<UEntry<'a>>,
     ) -> Result<()>;
     fn characteristics(&self) -> &dyn UpdateInto;
-}
-autobox!(Backend);
-
-pub struct HarfbuzzBackend;
-
-impl UpdateInto for HarfbuzzBackend {
-    fn update_into(&self, hasher: &mut dyn Hasher) {
-        hasher.update(b"harfbuzz_rs_now");
+    /// Whether the backend pins and limits the axes of variable fonts.
+    fn instances(&self) -> bool {
+        false
     }
 }
+autobox!(Backend);
 
-fn smart_load_font(data: &[u8]) -> Cow<[u8]> {
-    use std::borrow::Cow;
-
+fn smart_load_font(data: &[u8]) -> Result<Cow<[u8]>> {
     const WOFF_MAGIC: [u8; 4] = [0x77, 0x4F, 0x46, 0x46];
     const WOFF2_MAGIC: [u8; 4] = [0x77, 0x4F, 0x46, 0x32];
 
@@ -240,30 +518,179 @@ fn smart_load_font(data: &[u8]) -> Cow<[u8]> {
     fn is_woff2(data: &[u8]) -> bool {
         data.starts_with(&WOFF2_MAGIC)
     }
-    if is_woff2(data) {
-        Cow::Owned(woff::version2::decompress(data).expect("illegal woff2 file"))
+    let font = if is_woff2(data) {
+        Cow::Owned(woff::version2::decompress(data).ok_or(anyhow!("cannot decode woff2"))?)
     } else if is_woff(data) {
-        Cow::Owned(woff::version1::decompress(data).expect("illegal woff file"))
+        Cow::Owned(woff::version1::decompress(data).ok_or(anyhow!("cannot decode woff"))?)
     } else {
         Cow::Borrowed(data)
-    }
+    };
+    Ok(font)
 }
 
-fn smart_save_font(data: Cow<[u8]>, path: impl AsRef<OsStr>) -> Cow<[u8]> {
+fn smart_save_font(data: Cow<[u8]>, path: impl AsRef<OsStr>) -> Result<Cow<[u8]>> {
     let path = path.as_ref().to_string_lossy();
-    if path.ends_with("woff2") {
+    let font = if path.ends_with("woff2") {
         Cow::Owned(
             woff::version2::compress(&data, String::new(), 1, true)
-                .expect("fail to compress woff2"),
+                .ok_or(anyhow!("cannot encode woff2"))?,
         )
     } else if path.ends_with("woff") {
-        Cow::Owned(woff::version1::compress(&data, 0, 0).expect("fail to compress woff"))
+        Cow::Owned(woff::version1::compress(&data, 0, 0).ok_or(anyhow!("cannot encode woff"))?)
     } else {
         data
+    };
+    Ok(font)
+}
+
+/// Subsets with allsorts in pure Rust, i.e., without HarfBuzz.
+///
+/// allsorts renumbers the glyphs but cannot subset `GSUB`, `GPOS` and `GDEF`, so fonts with
+/// them are rejected unless `drop_layout` is set.
+pub struct AllsortsBackend {
+    drop_layout: bool,
+}
+
+impl UpdateInto for AllsortsBackend {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        hasher.update(b"allsorts");
+        hasher.update(&[self.drop_layout as u8]);
+    }
+}
+
+impl AllsortsBackend {
+    const LAYOUT_TABLES: [(u32, &'static str); 3] = [
+        (allsorts::tag::GSUB, "GSUB"),
+        (allsorts::tag::GPOS, "GPOS"),
+        (allsorts::tag::GDEF, "GDEF"),
+    ];
+
+    fn new(config: &FontBuilderConfig) -> Result<Self> {
+        let AllsortsConfig { drop_layout } = config.allsorts;
+        Ok(Self { drop_layout })
+    }
+
+    /// Glyph ids of the chars present in the font, led by `.notdef` as allsorts expects.
+    fn glyph_ids<T: FontTableProvider>(font: &mut allsorts::Font<T>, chars: &[char]) -> Vec<u16> {
+        use allsorts::font::MatchingPresentation;
+        let mut glyph_ids = chars
+            .iter()
+            .map(|&ch| {
+                font.lookup_glyph_index(ch, MatchingPresentation::NotRequired, None)
+                    .0
+            })
+            .filter(|&gid| gid != 0)
+            .collect::<Vec<_>>();
+        glyph_ids.sort_unstable();
+        glyph_ids.dedup();
+        glyph_ids.insert(0, 0);
+        glyph_ids
+    }
+
+    fn subset(&self, font_bytes: &[u8], face_index: u32, chars: &[char]) -> Result<Vec<u8>> {
+        use allsorts::{binary::read::ReadScope, font_data::FontData, subset::subset};
+        let font_file = ReadScope::new(font_bytes)
+            .read::<FontData>()
+            .map_err(parse_error)?;
+        let provider = font_file
+            .table_provider(face_index as usize)
+            .map_err(parse_error)?;
+        let layout_tables = Self::LAYOUT_TABLES
+            .iter()
+            .filter(|(tag, _)| provider.has_table(*tag))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        if !layout_tables.is_empty() && !self.drop_layout {
+            bail!(
+                "allsorts cannot subset the {} tables of the font, \
+                 use the harfbuzz backend or set `drop_layout`",
+                layout_tables.join(", ")
+            );
+        }
+        let mut font = allsorts::Font::new(provider).map_err(parse_error)?;
+        let glyph_ids = Self::glyph_ids(&mut font, chars);
+        let new_face_data = subset(&font.font_table_provider, &glyph_ids)
+            .map_err(|reason| anyhow!("fail to subset font: {}", reason))?;
+        let new_face_data =
+            Self::copy_os2(&font.font_table_provider, &new_face_data)?.unwrap_or(new_face_data);
+        Ok(new_face_data)
+    }
+
+    /// Copies `OS/2`, which allsorts leaves out of TrueType subsets, from the source font.
+    ///
+    /// Returns `None` if `font_bytes` needs no change.
+    fn copy_os2(source: &impl FontTableProvider, font_bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+        use allsorts::{binary::read::ReadScope, font_data::FontData, tag};
+        let Some(os2) = source.table_data(tag::OS_2).map_err(parse_error)? else {
+            return Ok(None);
+        };
+        let font_file = ReadScope::new(font_bytes)
+            .read::<FontData>()
+            .map_err(parse_error)?;
+        let provider = font_file.table_provider(0).map_err(parse_error)?;
+        if provider.has_table(tag::OS_2) {
+            return Ok(None);
+        }
+        let mut tables = vec![(tag::OS_2, os2)];
+        for tag in provider.table_tags().unwrap_or_default() {
+            tables.push((tag, provider.read_table_data(tag).map_err(parse_error)?));
+        }
+        Ok(Some(write_sfnt(&font_bytes[..4], tables)))
+    }
+}
+
+/// Assembles an sfnt from its version and tables, filling in the checksums.
+fn write_sfnt(sfnt_version: &[u8], mut tables: Vec<(u32, Cow<[u8]>)>) -> Vec<u8> {
+    const HEAD: u32 = u32::from_be_bytes(*b"head");
+    fn checksum(data: &[u8]) -> u32 {
+        data.chunks(4).fold(0u32, |sum, chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            sum.wrapping_add(u32::from_be_bytes(word))
+        })
+    }
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = 16 << entry_selector;
+    let mut font = sfnt_version.to_vec();
+    for field in [
+        num_tables,
+        search_range,
+        entry_selector,
+        num_tables * 16 - search_range,
+    ] {
+        font.extend(field.to_be_bytes());
+    }
+    let mut offset = font.len() + 16 * tables.len();
+    let mut head_offset = None;
+    for (tag, data) in &mut tables {
+        if *tag == HEAD && data.len() >= 12 {
+            data.to_mut()[8..12].fill(0);
+            head_offset = Some(offset);
+        }
+        font.extend(tag.to_be_bytes());
+        font.extend(checksum(data).to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
     }
+    for (_, data) in &tables {
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0AFBA_u32.wrapping_sub(checksum(&font));
+        font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+fn parse_error(reason: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("cannot parse font: {}", reason)
 }
 
-impl Backend for HarfbuzzBackend {
+impl Backend for AllsortsBackend {
     fn characteristics(&self) -> &dyn UpdateInto {
         self
     }
@@ -273,30 +700,10 @@ impl Backend for HarfbuzzBackend {
         dest_info: &mut DestInfo,
         entry: Arc<UEntry<'a>>,
     ) -> Result<()> {
-        use harfbuzz_rs_now::subset::Subset;
-        use harfbuzz_rs_now::Face;
-        let subset = Subset::new();
-        subset.clear_drop_table();
-        subset.adjust_layout();
-        let chars = entry
-            .range
-            .as_chars()
-            .map(|ch| ch as u32)
-            .collect::<Vec<_>>();
-        subset.add_chars(&chars);
-        let file_bytes = ctx
-            .source
-            .file()
-            .content()
-            .map_err(|reason| anyhow!("fail to open file: {:?}", reason))?;
-        let font_bytes = smart_load_font(file_bytes);
-        let face = Face::from_bytes(&font_bytes, 0);
-        let new_face = subset.run_subset(&face);
-        let new_face_data = new_face.face_data();
-        let new_binary = smart_save_font(
-            Cow::Borrowed(new_face_data.get_data()),
-            &dest_info.file_path,
-        );
+        let font_bytes = ctx.sfnt()?;
+        let chars = entry.range.as_chars().collect::<Vec<_>>();
+        let new_face_data = self.subset(&font_bytes, ctx.face_index, &chars)?;
+        let new_binary = smart_save_font(Cow::Owned(new_face_data), &dest_info.file_path)?;
         {
             let file_guard = dest_info.as_writable()?;
             fs::write(file_guard.path(), new_binary)?;
@@ -306,17 +713,139 @@ impl Backend for HarfbuzzBackend {
     }
 }
 
-pub struct PyftBackend;
+/// Runs an external subsetter, e.g., `pyftsubset`, with templated arguments.
+pub struct CommandBackend {
+    program: PathBuf,
+    args: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl CommandBackend {
+    fn new(config: &FontBuilderConfig) -> Result<Self> {
+        let Some(CommandConfig {
+            program,
+            args,
+            timeout,
+        }) = &config.command
+        else {
+            bail!("the command backend requires [builder.font.command]");
+        };
+        if !args.iter().any(|arg| arg.contains("{output}")) {
+            bail!("the arguments of [builder.font.command] must contain {{output}}");
+        }
+        Ok(Self {
+            program: program.clone(),
+            args: args.clone(),
+            timeout: timeout.map(Duration::from_secs_f64),
+        })
+    }
+
+    fn pyftsubset() -> Self {
+        Self {
+            program: "pyftsubset".into(),
+            args: [
+                "{input}",
+                "--font-number={face_index}",
+                "--text-file={text_file}",
+                "--output-file={output}",
+                "--ignore-missing-glyphs",
+                "--no-subset-tables+=FFTM,morx,feat",
+            ]
+            .map(String::from)
+            .into(),
+            timeout: None,
+        }
+    }
+
+    fn uses(&self, var: &str) -> bool {
+        let var = format!("{{{var}}}");
+        self.args.iter().any(|arg| arg.contains(&var))
+    }
+
+    /// Replaces the `{name}`s in `arg` by their values in `vars`.
+    fn render_arg(arg: &str, vars: &[(&str, &OsStr)]) -> OsString {
+        let mut out = OsString::new();
+        let mut rest = arg;
+        while let Some(start) = rest.find('{') {
+            out.push(&rest[..start]);
+            rest = &rest[start..];
+            let var = vars.iter().find(|(name, _)| {
+                rest[1..].starts_with(name) && rest[1 + name.len()..].starts_with('}')
+            });
+            match var {
+                Some((name, value)) => {
+                    out.push(value);
+                    rest = &rest[name.len() + 2..];
+                }
+                None => {
+                    out.push("{");
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push(rest);
+        out
+    }
+
+    fn run(&self, args: &[OsString]) -> Result<()> {
+        use std::{io::Read, process::Stdio, time::Instant};
+        let program = self.program.display();
+        let mut child = std::process::Command::new(&self.program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|reason| anyhow!("fail to run {}: {}", program, reason))?;
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr = std::thread::spawn(move || {
+            let mut buffer = String::new();
+            let _ = stderr.read_to_string(&mut buffer);
+            buffer
+        });
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                child.kill()?;
+                child.wait()?;
+                break None;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        let stderr = stderr.join().unwrap_or_default();
+        match status {
+            None => bail!(
+                "{} timed out after {:?}: {}",
+                program,
+                self.timeout.unwrap(),
+                stderr.trim()
+            ),
+            Some(status) if !status.success() => bail!(
+                "{} failed with code {:?}: {}",
+                program,
+                status.code(),
+                stderr.trim()
+            ),
+            Some(_) => Ok(()),
+        }
+    }
+}
 
-impl UpdateInto for PyftBackend {
+impl UpdateInto for CommandBackend {
     fn update_into(&self, hasher: &mut dyn Hasher) {
-        hasher.update(b"pyftsubset");
-        hasher.update(b"--ignore-missing-glyphs");
-        hasher.update(b"--no-subset-tables+=FFTM,morx,feat");
+        hasher.update(b"command");
+        hasher.update(self.program.as_os_str().as_encoded_bytes());
+        for arg in &self.args {
+            hasher.update(&arg.len().to_le_bytes());
+            hasher.update(arg.as_bytes());
+        }
     }
 }
 
-impl Backend for PyftBackend {
+impl Backend for CommandBackend {
     fn characteristics(&self) -> &dyn UpdateInto {
         self
     }
@@ -327,32 +856,45 @@ impl Backend for PyftBackend {
         entry: Arc<UEntry<'a>>,
     ) -> Result<()> {
         use std::io::Write;
-        let unicode_path = {
+        fn temp_file(content: String) -> Result<tempfile::TempPath> {
             let mut file = tempfile::Builder::new().tempfile()?;
-            let content = entry.range.as_chars().collect::<String>();
             file.write_all(content.as_bytes())?;
-            file.into_temp_path()
-        };
-        let font_dest = dest_info.as_writable()?;
-        let mut p = std::process::Command::new("pyftsubset")
-            .arg(ctx.source.file().path())
-            .arg(
-                [OsStr::new("--text-file="), unicode_path.as_os_str()]
-                    .into_iter()
-                    .collect::<OsString>(),
-            )
-            .arg(
-                [OsStr::new("--output-file="), font_dest.path().as_ref()]
-                    .into_iter()
-                    .collect::<OsString>(),
-            )
-            .arg("--ignore-missing-glyphs")
-            .arg("--no-subset-tables+=FFTM,morx,feat")
-            .spawn()?;
-        let status = p.wait()?;
-        if !status.success() {
-            bail!("pyftsubset failed with code: {:?}", status.code());
+            Ok(file.into_temp_path())
         }
+        let text_file = self
+            .uses("text_file")
+            .then(|| temp_file(entry.range.as_chars().collect()))
+            .transpose()?;
+        let unicodes_file = self
+            .uses("unicodes_file")
+            .then(|| {
+                let codes = entry.range.as_chars();
+                temp_file(codes.map(|ch| format!("U+{:04X}\n", ch as u32)).collect())
+            })
+            .transpose()?;
+        let face_index = OsString::from(ctx.face_index.to_string());
+        let font_dest = dest_info.as_writable()?;
+        let vars = [
+            ("input", ctx.source.file().path().as_os_str()),
+            ("face_index", &face_index),
+            ("output", font_dest.path()),
+            (
+                "text_file",
+                text_file.as_deref().map_or(OsStr::new(""), Path::as_os_str),
+            ),
+            (
+                "unicodes_file",
+                unicodes_file
+                    .as_deref()
+                    .map_or(OsStr::new(""), Path::as_os_str),
+            ),
+        ];
+        let args = self
+            .args
+            .iter()
+            .map(|arg| Self::render_arg(arg, &vars))
+            .collect::<Vec<_>>();
+        self.run(&args)?;
         font_dest.commit()?;
         Ok(())
     }
@@ -371,6 +913,7 @@ impl<'a> History<'a> {
         })
     }
 
+    /// Files of the previous builds of the entry, in any format.
     fn query<'d: 's, 's>(&'s self, dest_info: &'d DestInfo) -> Option<&'s [PathBuf]> {
         let token = FileNameMatcher::new(&dest_info.tmpl.name_template)
             .match_as_token(dest_info.file_path.file_name().unwrap().to_string_lossy())
@@ -440,31 +983,26 @@ impl<'a> FileNameMatcher<'a> {
             ..Default::default()
         }
     }
+    /// Strips the affixes and the digest from `name`, matching `<EXT>` with any extension.
+    fn mid_prefix<'n>(&self, name: &'n str) -> Option<&'n str> {
+        let mid = name.strip_prefix(self.prefix)?;
+        let mid = match self.suffix.split_once(FontOutputTmplParams::METAVARS[1]) {
+            Some((before, after)) => mid.strip_suffix(after)?.rsplit_once(before)?.0,
+            None => mid.strip_suffix(self.suffix)?,
+        };
+        Some(mid.rsplit_once('_')?.0)
+    }
     fn matches<'b>(&self, name: Cow<'b, str>) -> Result<Cow<'b, str>, Cow<'b, str>> {
         match name {
             Cow::Owned(mut name) => {
-                let Some(mid) = name.strip_prefix(self.prefix) else {
-                    return Err(name.into());
-                };
-                let Some(mid) = mid.strip_suffix(self.suffix) else {
-                    return Err(name.into());
-                };
-                let Some((mid_prefix, _)) = mid.rsplit_once('_') else {
+                let Some(mid_prefix) = self.mid_prefix(&name) else {
                     return Err(name.into());
                 };
                 let mid_prefix_range = name.get_substr_range(mid_prefix).unwrap();
                 name.retain_range(mid_prefix_range);
                 Ok(name.into())
             }
-            Cow::Borrowed(name) => {
-                let mid = name
-                    .strip_prefix(self.prefix)
-                    .ok_or(name)?
-                    .strip_suffix(self.suffix)
-                    .ok_or(name)?;
-                let mid_prefix = mid.rsplit_once('_').ok_or(name)?.0;
-                Ok(Cow::Borrowed(mid_prefix))
-            }
+            Cow::Borrowed(name) => self.mid_prefix(name).map(Cow::Borrowed).ok_or(name.into()),
         }
     }
     fn match_as_token(&self, name: Cow<'a, str>) -> Result<Token<'a>, Cow<'a, str>> {
@@ -475,3 +1013,85 @@ impl<'a> FileNameMatcher<'a> {
         })
     }
 }
+
+#[cfg(test)]
+const FIXTURE_FONT: &str = "fixtures/DejaVuSans-subset.ttf";
+
+#[test]
+fn test_allsorts_subset() {
+    use allsorts::{binary::read::ReadScope, font_data::FontData, tag};
+    let font_bytes = fs::read(FIXTURE_FONT).unwrap();
+    let chars = ['a', 'b', 'Ж'];
+    let error = AllsortsBackend { drop_layout: false }
+        .subset(&font_bytes, 0, &chars)
+        .err();
+    assert!(error.is_some_and(|e| e.to_string().contains("GSUB, GPOS")));
+
+    let new_face_data = AllsortsBackend { drop_layout: true }
+        .subset(&font_bytes, 0, &chars)
+        .unwrap();
+    let font_file = ReadScope::new(&new_face_data).read::<FontData>().unwrap();
+    let provider = font_file.table_provider(0).unwrap();
+    for table in [
+        tag::CMAP,
+        tag::GLYF,
+        tag::HEAD,
+        tag::HHEA,
+        tag::HMTX,
+        tag::LOCA,
+        tag::MAXP,
+        tag::NAME,
+        tag::OS_2,
+        tag::POST,
+    ] {
+        assert!(provider.has_table(table), "{}", tag::DisplayTag(table));
+    }
+    assert!(!provider.has_table(tag::GSUB));
+    assert_eq!(allsorts::Font::new(provider).unwrap().num_glyphs(), 4);
+}
+
+#[test]
+fn test_render_arg() {
+    let vars = [
+        ("input", OsStr::new("a.ttf")),
+        ("output", OsStr::new("b.woff2")),
+    ];
+    assert_eq!(
+        CommandBackend::render_arg("--output-file={output}", &vars),
+        "--output-file=b.woff2"
+    );
+    assert_eq!(
+        CommandBackend::render_arg("{input}{x}{output", &vars),
+        "a.ttf{x}{output"
+    );
+}
+
+#[test]
+fn test_file_name_matcher() {
+    let tmpl = Tmpl::new_str("XH_<FID>.<EXT>");
+    let matcher = FileNameMatcher::new(&tmpl);
+    let matches = |name: &'static str| matcher.matches(Cow::Borrowed(name)).ok();
+    assert_eq!(matches("XH_12_abcdefgh.woff2").as_deref(), Some("12"));
+    assert_eq!(matches("XH_12_abcdefgh.woff").as_deref(), Some("12"));
+    assert_eq!(matches("XH_12.woff2"), None);
+    assert_eq!(matches("YH_12_abcdefgh.woff2"), None);
+}
+
+#[test]
+fn test_build_failures_merge() {
+    let failure = |entry: &str| BuildFailures {
+        chunks: 2,
+        errors: vec![ChunkError {
+            font: "a.ttf".into(),
+            entry: entry.into(),
+            stage: "subset",
+            error: anyhow!("bad glyph"),
+        }],
+    };
+    let merged = BuildFailures::merge([Ok(1), Err(failure("0").into()), Err(failure("1").into())]);
+    assert_eq!(
+        merged.unwrap_err().to_string(),
+        "2 of 4 chunks failed:\n  cannot subset chunk 0 of font a.ttf: bad glyph\n  cannot subset chunk 1 of font a.ttf: bad glyph"
+    );
+    assert_eq!(BuildFailures::merge([Ok(1), Ok(2)]).unwrap(), [1, 2]);
+}
@@ -0,0 +1,315 @@
+//! The default backend, subsetting with HarfBuzz.
+
+use std::{borrow::Cow, fs, sync::Arc};
+
+use anyhow::{bail, Result};
+use fontchan_unicode::UEntry;
+use harfbuzz_rs_now::{subset::Subset, Blob, Face, HarfbuzzObject, Owned, Shared};
+
+#[cfg(test)]
+use super::FIXTURE_FONT;
+use super::{smart_save_font, Backend, Context, DestInfo};
+use crate::{
+    builder::hb_subset,
+    config::{AxisPin, FontBuilderConfig, HarfbuzzConfig},
+};
+use fontchan_util::{Hasher, UpdateInto};
+
+impl Context {
+    /// The HarfBuzz face of the source, shared by the threads.
+    fn face(&self) -> Result<Shared<Face<'static>>> {
+        let cache = self.source_cache();
+        if let Some(face) = cache.face.get() {
+            return Ok(face.clone());
+        }
+        let blob = Blob::with_bytes_owned(self.sfnt()?, |sfnt| &sfnt[..]);
+        let face = Face::new(blob, self.face_index);
+        if face.glyph_count() == 0 {
+            bail!("cannot load face {} of the font", self.face_index);
+        }
+        Ok(cache.face.get_or_init(|| face.into()).clone())
+    }
+}
+
+/// Converts a table or feature name, e.g., `cvt`, to its OpenType tag.
+fn ot_tag(name: &str) -> Result<u32> {
+    if name.is_empty() || name.len() > 4 || !name.bytes().all(|b| b.is_ascii_graphic()) {
+        bail!("invalid OpenType tag: {:?}", name);
+    }
+    let mut tag = *b"    ";
+    tag[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(u32::from_be_bytes(tag))
+}
+
+fn ot_tags(names: &[String]) -> Result<Vec<u32>> {
+    names.iter().map(|name| ot_tag(name)).collect()
+}
+
+pub struct HarfbuzzBackend {
+    keep_features: Option<Vec<u32>>,
+    drop_features: Vec<u32>,
+    drop_tables: Vec<u32>,
+    name_ids: Option<Vec<u16>>,
+    flags: u32,
+}
+
+impl HarfbuzzBackend {
+    pub(super) fn new(config: &FontBuilderConfig) -> Result<Self> {
+        let HarfbuzzConfig {
+            keep_features,
+            drop_features,
+            drop_tables,
+            no_hinting,
+            name_ids,
+            glyph_names,
+            retain_gids,
+        } = &config.harfbuzz;
+        let flags = [
+            (*no_hinting, hb_subset::HB_SUBSET_FLAGS_NO_HINTING),
+            (*retain_gids, hb_subset::HB_SUBSET_FLAGS_RETAIN_GIDS),
+            (*glyph_names, hb_subset::HB_SUBSET_FLAGS_GLYPH_NAMES),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .fold(0, |flags, (_, flag)| flags | flag);
+        Ok(Self {
+            keep_features: keep_features.as_deref().map(ot_tags).transpose()?,
+            drop_features: ot_tags(drop_features)?,
+            drop_tables: ot_tags(drop_tables)?,
+            name_ids: name_ids.clone(),
+            flags,
+        })
+    }
+}
+
+impl UpdateInto for HarfbuzzBackend {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        hasher.update(b"harfbuzz_rs_now");
+        match &self.keep_features {
+            Some(tags) => {
+                hasher.update(b"keep_features");
+                for tag in tags {
+                    hasher.update(&tag.to_be_bytes());
+                }
+            }
+            None => hasher.update(b"all_features"),
+        }
+        hasher.update(b"drop_features");
+        for tag in &self.drop_features {
+            hasher.update(&tag.to_be_bytes());
+        }
+        hasher.update(b"drop_tables");
+        for tag in &self.drop_tables {
+            hasher.update(&tag.to_be_bytes());
+        }
+        if let Some(name_ids) = &self.name_ids {
+            hasher.update(b"name_ids");
+            for id in name_ids {
+                hasher.update(&id.to_le_bytes());
+            }
+        }
+        hasher.update(b"flags");
+        hasher.update(&self.flags.to_le_bytes());
+    }
+}
+
+/// [`Subset`] configured from the options of [`HarfbuzzBackend`].
+struct SubsetInput(Owned<Subset<'static>>);
+
+impl SubsetInput {
+    fn new(
+        backend: &HarfbuzzBackend,
+        face: &Face,
+        axes: &[AxisPin],
+        chars: impl Iterator<Item = char>,
+    ) -> Result<Self> {
+        use hb_subset::*;
+        let subset = Subset::new();
+        subset.clear_drop_table();
+        subset.adjust_layout();
+        subset.add_chars(&chars.map(|ch| ch as u32).collect::<Vec<_>>());
+        let input = subset.as_raw().cast::<hb_subset_input_t>();
+        unsafe {
+            let tables = hb_subset_input_set(input, HB_SUBSET_SETS_DROP_TABLE_TAG);
+            for &tag in &backend.drop_tables {
+                hb_set_add(tables, tag);
+            }
+            if let Some(name_ids) = &backend.name_ids {
+                let names = hb_subset_input_set(input, HB_SUBSET_SETS_NAME_ID);
+                hb_set_clear(names);
+                for &id in name_ids {
+                    hb_set_add(names, id as u32);
+                }
+            }
+            let features = hb_subset_input_set(input, HB_SUBSET_SETS_LAYOUT_FEATURE_TAG);
+            hb_set_clear(features);
+            match &backend.keep_features {
+                Some(tags) => tags.iter().for_each(|&tag| hb_set_add(features, tag)),
+                None => hb_set_invert(features),
+            }
+            for &tag in &backend.drop_features {
+                hb_set_del(features, tag);
+            }
+            hb_subset_input_set_flags(input, hb_subset_input_get_flags(input) | backend.flags);
+        }
+        for axis in axes {
+            let tag = ot_tag(&axis.tag)?;
+            let face = face.as_raw().cast::<hb_face_t>();
+            let applied = unsafe {
+                if axis.min == axis.max {
+                    hb_subset_input_pin_axis_location(input, face, tag, axis.min)
+                } else {
+                    let default = f32::NAN;
+                    hb_subset_input_set_axis_range(input, face, tag, axis.min, axis.max, default)
+                }
+            };
+            if applied == 0 {
+                bail!("fail to instance axis {} of the font", axis.tag);
+            }
+        }
+        Ok(Self(subset))
+    }
+
+    #[cfg(test)]
+    fn keeps_feature(&self, tag: u32) -> bool {
+        use hb_subset::*;
+        let input = self.0.as_raw().cast::<hb_subset_input_t>();
+        unsafe {
+            let features = hb_subset_input_set(input, HB_SUBSET_SETS_LAYOUT_FEATURE_TAG);
+            hb_set_has(features, tag) != 0
+        }
+    }
+
+    /// Unlike [`Subset::run_subset`], fails instead of panicking.
+    fn run<'a>(&self, face: &Face<'a>) -> Result<Owned<Face<'a>>> {
+        let new_face =
+            unsafe { hb_subset::hb_subset_or_fail(face.as_raw().cast(), self.0.as_raw().cast()) };
+        if new_face.is_null() {
+            bail!("harfbuzz fails to subset the font");
+        }
+        Ok(unsafe { Owned::from_raw(new_face.cast()) })
+    }
+}
+
+impl Backend for HarfbuzzBackend {
+    fn characteristics(&self) -> &dyn UpdateInto {
+        self
+    }
+    fn instances(&self) -> bool {
+        true
+    }
+    fn do_subset<'a>(
+        &self,
+        ctx: &Context,
+        dest_info: &mut DestInfo,
+        entry: Arc<UEntry<'a>>,
+    ) -> Result<()> {
+        let face = ctx.face()?;
+        let input = SubsetInput::new(self, &face, &ctx.axes, entry.range.as_chars())?;
+        let new_face = input.run(&face)?;
+        let new_face_data = new_face.face_data();
+        let new_binary = smart_save_font(
+            Cow::Borrowed(new_face_data.get_data()),
+            &dest_info.file_path,
+        )?;
+        {
+            let file_guard = dest_info.as_writable()?;
+            fs::write(file_guard.path(), new_binary)?;
+            file_guard.commit()?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_ot_tag() {
+    assert_eq!(ot_tag("vert").unwrap(), u32::from_be_bytes(*b"vert"));
+    assert_eq!(ot_tag("cvt").unwrap(), u32::from_be_bytes(*b"cvt "));
+    assert!(ot_tag("kern ").is_err());
+    assert!(ot_tag("").is_err());
+}
+
+#[test]
+fn test_harfbuzz_subset() {
+    use allsorts::{
+        binary::read::ReadScope,
+        font_data::FontData,
+        layout::{LayoutTable, GSUB},
+        tables::FontTableProvider,
+        tag,
+    };
+    let gsub_scripts = |data: &[u8]| {
+        let font_file = ReadScope::new(data).read::<FontData>().unwrap();
+        let provider = font_file.table_provider(0).unwrap();
+        let gsub = provider.read_table_data(tag::GSUB).unwrap();
+        let gsub = ReadScope::new(&gsub).read::<LayoutTable<GSUB>>().unwrap();
+        let scripts = gsub.opt_script_list.unwrap();
+        let scripts = scripts.script_records().iter();
+        scripts.map(|record| record.script_tag).collect::<Vec<_>>()
+    };
+    let font_bytes = fs::read(FIXTURE_FONT).unwrap();
+    let face = Face::from_bytes(&font_bytes, 0);
+    let backend = HarfbuzzBackend::new(&Default::default()).unwrap();
+    let input = SubsetInput::new(&backend, &face, &[], "abЖ".chars()).unwrap();
+    assert!(input.keeps_feature(ot_tag("palt").unwrap()));
+    let new_face = input.run(&face).unwrap();
+    assert!(new_face.glyph_count() < face.glyph_count());
+    let scripts = gsub_scripts(new_face.face_data().get_data());
+    for script in ["DFLT", "cyrl", "grek", "latn"] {
+        assert!(scripts.contains(&ot_tag(script).unwrap()), "{script}");
+    }
+    assert_eq!(scripts, gsub_scripts(&font_bytes));
+
+    let config = FontBuilderConfig {
+        harfbuzz: HarfbuzzConfig {
+            drop_tables: vec!["GPOS".to_owned()],
+            drop_features: vec!["palt".to_owned()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let backend = HarfbuzzBackend::new(&config).unwrap();
+    let input = SubsetInput::new(&backend, &face, &[], "ab".chars()).unwrap();
+    assert!(!input.keeps_feature(ot_tag("palt").unwrap()));
+    assert!(input.keeps_feature(ot_tag("kern").unwrap()));
+    let new_face_data = input.run(&face).unwrap().face_data();
+    let font_file = ReadScope::new(new_face_data.get_data())
+        .read::<FontData>()
+        .unwrap();
+    let provider = font_file.table_provider(0).unwrap();
+    assert!(!provider.has_table(tag::GPOS));
+    assert!(provider.has_table(tag::GSUB));
+}
+
+#[test]
+fn test_harfbuzz_axes() {
+    let font_bytes = fs::read(FIXTURE_FONT).unwrap();
+    let face = Face::from_bytes(&font_bytes, 0);
+    let backend = HarfbuzzBackend::new(&Default::default()).unwrap();
+    for axis in ["wght=400", "wght=300:700"] {
+        let axes = [axis.parse::<AxisPin>().unwrap()];
+        let error = SubsetInput::new(&backend, &face, &axes, "ab".chars()).err();
+        assert!(error.is_some_and(|e| e.to_string().contains("axis wght")));
+    }
+
+    use allsorts::{binary::read::ReadScope, font_data::FontData, tables::FontTableProvider};
+    // The fixture font with a `wght` axis of 100 to 900 in `fvar`, and no variations.
+    let font_bytes = fs::read("fixtures/DejaVuSans-subset-wght.ttf").unwrap();
+    let face = Face::from_bytes(&font_bytes, 0);
+    let fvar = |axis: &str| {
+        let axes = [axis.parse::<AxisPin>().unwrap()];
+        let input = SubsetInput::new(&backend, &face, &axes, "ab".chars()).unwrap();
+        let new_face_data = input.run(&face).unwrap().face_data();
+        let font_file = ReadScope::new(new_face_data.get_data())
+            .read::<FontData>()
+            .unwrap();
+        let provider = font_file.table_provider(0).unwrap();
+        let fvar = provider.table_data(u32::from_be_bytes(*b"fvar")).unwrap();
+        fvar.map(|fvar| fvar.to_vec())
+    };
+    assert!(fvar("wght=400").is_none());
+    let fvar = fvar("wght=300:700").unwrap();
+    let fixed = |pos: usize| i32::from_be_bytes(fvar[pos..pos + 4].try_into().unwrap()) >> 16;
+    assert_eq!(&fvar[16..20], b"wght");
+    assert_eq!((fixed(20), fixed(24), fixed(28)), (300, 400, 700));
+}
@@ -0,0 +1,65 @@
+//! The parts of the HarfBuzz subset API that `harfbuzz_rs_now::subset::Subset` does not wrap.
+//!
+//! The symbols come from the HarfBuzz that `harfbuzz_rs_now` builds and links, so the
+//! pointers of its objects are passed through after a `cast()`.
+#![allow(non_camel_case_types)]
+
+use std::ffi::{c_float, c_int, c_uint};
+
+#[repr(C)]
+pub struct hb_subset_input_t {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct hb_set_t {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct hb_face_t {
+    _private: [u8; 0],
+}
+
+pub type hb_bool_t = c_int;
+pub type hb_tag_t = u32;
+pub type hb_codepoint_t = u32;
+
+pub const HB_SUBSET_SETS_DROP_TABLE_TAG: c_uint = 3;
+pub const HB_SUBSET_SETS_NAME_ID: c_uint = 4;
+pub const HB_SUBSET_SETS_LAYOUT_FEATURE_TAG: c_uint = 6;
+
+pub const HB_SUBSET_FLAGS_NO_HINTING: c_uint = 0x01;
+pub const HB_SUBSET_FLAGS_RETAIN_GIDS: c_uint = 0x02;
+pub const HB_SUBSET_FLAGS_GLYPH_NAMES: c_uint = 0x80;
+
+extern "C" {
+    pub fn hb_subset_input_set(input: *mut hb_subset_input_t, set_type: c_uint) -> *mut hb_set_t;
+    pub fn hb_subset_input_get_flags(input: *mut hb_subset_input_t) -> c_uint;
+    pub fn hb_subset_input_set_flags(input: *mut hb_subset_input_t, value: c_uint);
+    pub fn hb_subset_input_pin_axis_location(
+        input: *mut hb_subset_input_t,
+        face: *mut hb_face_t,
+        axis_tag: hb_tag_t,
+        axis_value: c_float,
+    ) -> hb_bool_t;
+    pub fn hb_subset_input_set_axis_range(
+        input: *mut hb_subset_input_t,
+        face: *mut hb_face_t,
+        axis_tag: hb_tag_t,
+        axis_min_value: c_float,
+        axis_max_value: c_float,
+        axis_def_value: c_float,
+    ) -> hb_bool_t;
+    pub fn hb_subset_or_fail(
+        source: *mut hb_face_t,
+        input: *mut hb_subset_input_t,
+    ) -> *mut hb_face_t;
+
+    pub fn hb_set_add(set: *mut hb_set_t, codepoint: hb_codepoint_t);
+    pub fn hb_set_del(set: *mut hb_set_t, codepoint: hb_codepoint_t);
+    #[cfg(test)]
+    pub fn hb_set_has(set: *const hb_set_t, codepoint: hb_codepoint_t) -> hb_bool_t;
+    pub fn hb_set_clear(set: *mut hb_set_t);
+    pub fn hb_set_invert(set: *mut hb_set_t);
+}
@@ -5,7 +5,7 @@ use fontchan_util::{AtomicPath, Digester};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
 use crate::paramdef;
 
@@ -22,6 +22,17 @@ paramdef!(
     sha = "\"{%SHA%}\""
 );
 
+static PAGES_JS_TEMPLATE: Tmpl<PagesJSTmplParams> = Tmpl::new_str(include_str!("page_loader.js"));
+
+paramdef!(
+    PagesJSTmplParams,
+    pages_js_params,
+    font_specs = "\"{%FONT_SPECS%}\"",
+    routes = "\"{%ROUTES%}\"",
+    fids = "\"{%FIDS%}\"",
+    sha = "\"{%SHA%}\""
+);
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CSSFragments<'a> {
     pub ext: Cow<'a, str>,
@@ -42,7 +53,34 @@ impl Builder {
 
         let range_data = encode_urange_data(ranges)?;
         let fid_data = encode_fid_data(font_results.entry_minor_iter().map(|r| r.fid.as_str()))?;
+        Self::build_with(dest, fragments, range_data, fid_data)
+    }
 
+    /// Builds the loader for fonts partitioned separately, with the partition
+    /// and the results of each font in the order of `fragments`.
+    pub fn build_per_font<'f>(
+        &self,
+        dest: AtomicPath,
+        fragments: impl Iterator<Item = &'f CSSFragments<'f>>,
+        fonts: &[(&[URange], &FontResults)],
+    ) -> Result<()> {
+        use fontchan_codec::*;
+
+        let range_data = encode_face_urange_data(fonts.iter().map(|(ranges, _)| ranges.iter()))?;
+        let fid_data = encode_fid_data(
+            fonts
+                .iter()
+                .flat_map(|(_, results)| results.entry_minor_iter().map(|r| r.fid.as_str())),
+        )?;
+        Self::build_with(dest, fragments, range_data, fid_data)
+    }
+
+    fn build_with<'f>(
+        dest: AtomicPath,
+        fragments: impl Iterator<Item = &'f CSSFragments<'f>>,
+        range_data: Vec<u8>,
+        fid_data: Vec<u8>,
+    ) -> Result<()> {
         let fragments = fragments.collect::<Vec<_>>();
         let estimated_heap_size = fontchan_codec::StdContext {
             writer: fontchan_codec::CounterWriter::new(),
@@ -71,6 +109,46 @@ impl Builder {
             sha = sha.as_ref()
         ));
 
+        Self::write(dest, &js)
+    }
+
+    /// Builds the loader for per-page subsets, which picks the subset by the page route.
+    pub fn build_pages<'f>(
+        &self,
+        dest: AtomicPath,
+        fragments: impl Iterator<Item = &'f CSSFragments<'f>>,
+        routes: &[(String, usize)],
+        font_results: &FontResults,
+    ) -> Result<()> {
+        let fragments = fragments.collect::<Vec<_>>();
+        let font_specs_bin = json!(fragments).to_string();
+        let routes_bin = json!(routes.iter().cloned().collect::<BTreeMap<_, _>>()).to_string();
+        let entry_count = routes.iter().map(|(_, idx)| idx + 1).max().unwrap_or(0);
+        let fids = (0..entry_count)
+            .map(|entry| {
+                (0..font_results.ctx_count())
+                    .map(|ctx| font_results.get(entry, ctx).fid.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let fids_bin = json!(fids).to_string();
+
+        let sha = Digester::new()
+            .push(font_specs_bin.as_bytes())
+            .push(routes_bin.as_bytes())
+            .push(fids_bin.as_bytes())
+            .base64_result();
+
+        let js = PAGES_JS_TEMPLATE.render(&pages_js_params!(
+            font_specs = font_specs_bin,
+            routes = routes_bin,
+            fids = fids_bin,
+            sha = sha.as_ref()
+        ));
+        Self::write(dest, &js)
+    }
+
+    fn write(dest: AtomicPath, js: &str) -> Result<()> {
         let dest = dest.into_writable()?;
         std::fs::write(dest.as_ref(), js.as_bytes())?;
         dest.commit()?;
@@ -1,7 +1,11 @@
 mod font;
+#[cfg(feature = "harfbuzz")]
+mod hb_subset;
 mod js;
+mod report;
 mod tmpl;
 
-pub use font::Builder as FontBuilder;
 pub use font::FontOutputTmpl;
+pub use font::{BuildFailures, Builder as FontBuilder};
 pub use js::{Builder as JSBuilder, CSSFragments};
+pub use report::{estimate_bytes_per_char, Builder as ReportBuilder};
@@ -0,0 +1,48 @@
+"use strict";
+(function () {
+  const global = globalThis || window;
+  const origin = global.location ? location.origin : global.$fontchanOrigin;
+  const fontSpecs = "{%FONT_SPECS%}";
+  const routes = "{%ROUTES%}";
+  const fids = "{%FIDS%}";
+  const sha = '"{%SHA%}"';
+  function lookup(pathname) {
+    const base = pathname.replace(/\/+$/, "");
+    const candidates = [pathname, base + "/index.html", base + ".html"];
+    for (const candidate of candidates) {
+      if (Object.prototype.hasOwnProperty.call(routes, candidate))
+        return routes[candidate];
+    }
+    return undefined;
+  }
+  async function decodeCss(pathname) {
+    if (pathname === undefined)
+      pathname = global.location ? location.pathname : "/";
+    const entry = lookup(decodeURI(pathname));
+    if (entry === undefined) return "";
+    let css = "";
+    fontSpecs.forEach(function (spec, i) {
+      const src = spec.src
+        .replace(/<ORIGIN>/g, origin)
+        .split("<FID>")
+        .join(fids[entry][i]);
+      css += "@font-face{" + spec.ext + src + "}";
+    });
+    return css;
+  }
+  async function injectCss() {
+    if (injectCss.__started) return;
+    injectCss.__started = true;
+    const cssData = await decodeCss();
+    if (!cssData) return;
+    const link = document.createElement("link");
+    link.rel = "stylesheet";
+    link.href = URL.createObjectURL(new Blob([cssData], { type: "text/css" }));
+    document.head.appendChild(link);
+  }
+  global.$fontchan = {
+    decodeCss: decodeCss,
+    injectCss: injectCss,
+    sha: sha,
+  };
+})();
@@ -0,0 +1,227 @@
+use std::{borrow::Cow, collections::HashMap, collections::HashSet, fmt::Write, path::Path};
+
+use anyhow::{anyhow, Result};
+use fontchan_partition::Page;
+use fontchan_unicode::{UEntry, URange};
+use fontchan_util::AtomicPath;
+use serde::Serialize;
+
+use super::font::BuildResults as FontResults;
+use crate::config::{FontConfig, ReportBuilderConfig};
+
+const SAMPLE_SIZE: usize = 16;
+
+#[derive(Serialize)]
+struct Report {
+    chunks: Vec<ChunkReport>,
+    pages: Vec<PageReport>,
+}
+
+#[derive(Serialize)]
+struct ChunkReport {
+    name: String,
+    chars: usize,
+    estimated_bytes: u64,
+    actual_bytes: u64,
+    sample: String,
+}
+
+#[derive(Serialize)]
+struct PageReport {
+    path: Option<String>,
+    chars: usize,
+    chunks_needed: usize,
+    bytes_needed: u64,
+    missing: String,
+}
+
+/// Estimates the subset bytes per character from the source font sizes,
+/// summed over all fonts.
+///
+/// The fonts are assumed to cover the char base, or, without one, the chars of `ranges`.
+pub fn estimate_bytes_per_char<'a>(
+    fonts: &[FontConfig],
+    char_base_len: Option<usize>,
+    ranges: impl IntoIterator<Item = &'a URange>,
+) -> Result<f64> {
+    let mut font_bytes = 0;
+    for font in fonts {
+        let content = font.input_path.content().map_err(|e| {
+            anyhow!(
+                "cannot read font {}: {}",
+                font.input_path.path().display(),
+                e
+            )
+        })?;
+        font_bytes += content.len();
+    }
+    Ok(bytes_per_char(font_bytes, char_base_len, ranges))
+}
+
+fn bytes_per_char<'a>(
+    font_bytes: usize,
+    char_base_len: Option<usize>,
+    ranges: impl IntoIterator<Item = &'a URange>,
+) -> f64 {
+    let chars =
+        char_base_len.unwrap_or_else(|| ranges.into_iter().map(|r| r.as_chars().count()).sum());
+    font_bytes as f64 / chars.max(1) as f64
+}
+
+pub struct Builder;
+
+impl Builder {
+    pub fn build(
+        &self,
+        config: &ReportBuilderConfig,
+        fonts: &[FontConfig],
+        entries: &[UEntry],
+        font_results: &FontResults,
+        pages: Option<Cow<[Page]>>,
+        char_base: Option<Cow<HashSet<char>>>,
+    ) -> Result<()> {
+        let report = Self::collect(fonts, entries, font_results, pages, char_base)?;
+        if let Some(path) = &config.json_path {
+            Self::write(path, &serde_json::to_string_pretty(&report)?)?;
+        }
+        if let Some(path) = &config.html_path {
+            Self::write(path, &Self::render_html(&report)?)?;
+        }
+        Ok(())
+    }
+
+    fn collect(
+        fonts: &[FontConfig],
+        entries: &[UEntry],
+        font_results: &FontResults,
+        pages: Option<Cow<[Page]>>,
+        char_base: Option<Cow<HashSet<char>>>,
+    ) -> Result<Report> {
+        let bytes_per_char = estimate_bytes_per_char(
+            fonts,
+            char_base.as_ref().map(|cb| cb.len()),
+            entries.iter().map(|entry| entry.range),
+        )?;
+
+        let mut chunks = Vec::with_capacity(entries.len());
+        let mut owners = HashMap::<char, usize>::new();
+        for (idx, entry) in entries.iter().enumerate() {
+            let mut actual_bytes = 0;
+            for ctx in 0..font_results.ctx_count() {
+                let result = font_results.get(idx, ctx);
+                actual_bytes += std::fs::metadata(&result.file_path)?.len();
+            }
+            let chars = entry.range.as_chars().count();
+            for ch in entry.range.as_chars() {
+                owners.insert(ch, idx);
+            }
+            chunks.push(ChunkReport {
+                name: entry.name.as_ref().to_owned(),
+                chars,
+                estimated_bytes: (chars as f64 * bytes_per_char) as u64,
+                actual_bytes,
+                sample: entry.range.as_chars().take(SAMPLE_SIZE).collect(),
+            });
+        }
+
+        let pages = pages
+            .iter()
+            .flat_map(|pages| pages.iter())
+            .map(|page| {
+                let mut needed = HashSet::new();
+                let mut missing = vec![];
+                for ch in page {
+                    if let Some(idx) = owners.get(ch) {
+                        needed.insert(*idx);
+                    }
+                    if char_base.as_ref().is_some_and(|cb| !cb.contains(ch)) {
+                        missing.push(*ch);
+                    }
+                }
+                missing.sort_unstable();
+                PageReport {
+                    path: page.path().map(|p| p.display().to_string()),
+                    chars: page.chars().len(),
+                    chunks_needed: needed.len(),
+                    bytes_needed: needed.iter().map(|idx| chunks[*idx].actual_bytes).sum(),
+                    missing: missing.into_iter().collect(),
+                }
+            })
+            .collect();
+
+        Ok(Report { chunks, pages })
+    }
+
+    fn render_html(report: &Report) -> Result<String> {
+        fn escape(input: &str) -> Cow<str> {
+            if !input.contains(['<', '>', '&', '"']) {
+                return Cow::Borrowed(input);
+            }
+            let mut out = String::with_capacity(input.len());
+            for ch in input.chars() {
+                match ch {
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    '&' => out.push_str("&amp;"),
+                    '"' => out.push_str("&quot;"),
+                    _ => out.push(ch),
+                }
+            }
+            Cow::Owned(out)
+        }
+
+        let mut html = String::new();
+        html.push_str(concat!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>fontchan report</title>",
+            "<style>body{font-family:sans-serif}table{border-collapse:collapse}",
+            "td,th{border:1px solid #ccc;padding:2px 6px}td.n{text-align:right}</style>",
+            "</head><body>"
+        ));
+        write!(html, "<h1>Chunks ({})</h1><table>", report.chunks.len())?;
+        html.push_str("<tr><th>name</th><th>chars</th><th>estimated bytes</th><th>actual bytes</th><th>sample</th></tr>");
+        for c in &report.chunks {
+            write!(
+                html,
+                "<tr><td>{}</td><td class=n>{}</td><td class=n>{}</td><td class=n>{}</td><td>{}</td></tr>",
+                escape(&c.name),
+                c.chars,
+                c.estimated_bytes,
+                c.actual_bytes,
+                escape(&c.sample)
+            )?;
+        }
+        html.push_str("</table>");
+        write!(html, "<h1>Pages ({})</h1><table>", report.pages.len())?;
+        html.push_str("<tr><th>path</th><th>chars</th><th>chunks needed</th><th>bytes needed</th><th>missing</th></tr>");
+        for p in &report.pages {
+            write!(
+                html,
+                "<tr><td>{}</td><td class=n>{}</td><td class=n>{}</td><td class=n>{}</td><td>{}</td></tr>",
+                escape(p.path.as_deref().unwrap_or("")),
+                p.chars,
+                p.chunks_needed,
+                p.bytes_needed,
+                escape(&p.missing)
+            )?;
+        }
+        html.push_str("</table></body></html>");
+        Ok(html)
+    }
+
+    fn write(path: &Path, content: &str) -> Result<()> {
+        let dest = AtomicPath::from(path).into_writable()?;
+        std::fs::write(dest.as_ref(), content.as_bytes())?;
+        dest.commit()?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_bytes_per_char() {
+    use fontchan_unicode::URangeBuilder;
+
+    let ranges = ["abc", "defg", ""].map(|s| URangeBuilder::from_chars(s.chars()).build());
+    assert_eq!(bytes_per_char(700, Some(70), &ranges), 10.0);
+    assert_eq!(bytes_per_char(700, None, &ranges), 100.0);
+    assert_eq!(bytes_per_char(700, None, &[]), 700.0);
+}
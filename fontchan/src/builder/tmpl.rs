@@ -52,10 +52,12 @@ where
                 }
             }
         }
-        for mvar in P::METAVARS {
-            if file_name.match_indices(mvar).count() != 1 {
+        // The first metavariable identifies the file, while the others are optional.
+        for (i, mvar) in P::METAVARS.iter().enumerate() {
+            let count = file_name.match_indices(mvar).count();
+            if count > 1 || (i == 0 && count == 0) {
                 bail!(
-                    "File name must contain exactly one instance of metavariable: {:?}",
+                    "File name must contain one instance of metavariable: {:?}",
                     mvar
                 );
             }
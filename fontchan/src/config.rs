@@ -1,11 +1,18 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
-use fontchan_util::{Con, Routine};
+use anyhow::{bail, Result};
+
+use fontchan_util::{Con, Hasher, Routine, UpdateInto};
 use serde::Deserialize;
 
 use fontchan_util::LazyFile;
 
-use crate::builder::FontOutputTmpl;
+use crate::builder::{CSSFragments, FontOutputTmpl};
 
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -16,6 +23,16 @@ pub struct Config {
     pub fonts: Vec<FontConfig>,
     pub builder: BuilderConfig,
     pub partition: fontchan_partition::Config,
+    pub per_page: Option<crate::per_page::PerPageConfig>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(Self::deserialize(toml::Deserializer::new(content))?)
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -23,7 +40,172 @@ pub struct Config {
 pub struct FontConfig {
     pub css: crate::builder::CSSFragments<'static>,
     pub input_path: Arc<LazyFile>,
+    /// Index of the face to use when `input_path` is a font collection, i.e., TTC or OTC.
+    #[serde(default)]
+    pub face_index: u32,
     pub output_tmpl: Arc<FontOutputTmpl>,
+    /// Partitions the font with the pages attributed to this `font-family`,
+    /// e.g., by the `cascade` pages provider, instead of sharing the partition.
+    pub family: Option<String>,
+    /// Variation axes to pin or limit before subsetting, e.g., `["wght=400"]` or `["wght=300:700"]`.
+    #[serde(default)]
+    pub axes: Vec<AxisPin>,
+    /// Formats to write each chunk in, filling `<EXT>` of `output_tmpl` and `css.src`.
+    #[serde(default)]
+    pub formats: Vec<FontFormat>,
+}
+
+impl FontConfig {
+    /// CSS fragments with the descriptors matching `axes` appended.
+    pub fn css_fragments(&self) -> CSSFragments<'_> {
+        let mut ext = Cow::Borrowed(&*self.css.ext);
+        let descriptors = axis_descriptors(&self.axes);
+        if !descriptors.is_empty() {
+            ext.to_mut().push_str(&descriptors);
+        }
+        let mut src = Cow::Borrowed(&*self.css.src);
+        if !self.formats.is_empty() && src.contains("<EXT>") {
+            src = Cow::Owned(expand_src(&src, &self.formats));
+        }
+        CSSFragments { ext, src }
+    }
+}
+
+/// The `font-weight` and `font-stretch` descriptors of the pinned or limited `wght` and `wdth`,
+/// and the `font-variation-settings` of the other limited axes.
+///
+/// Pinned axes are gone from the instanced font, so no `font-variation-settings` is set for them.
+/// Limited axes other than `wght` and `wdth` have no range descriptor, so they are set to the
+/// lower end of their range.
+fn axis_descriptors(axes: &[AxisPin]) -> String {
+    let mut descriptors = String::new();
+    for (tag, descriptor, unit) in [("wght", "font-weight", ""), ("wdth", "font-stretch", "%")] {
+        let Some(axis) = axes.iter().find(|axis| axis.tag == tag) else {
+            continue;
+        };
+        let value = if axis.min == axis.max {
+            format!("{}{unit}", axis.min)
+        } else {
+            format!("{}{unit} {}{unit}", axis.min, axis.max)
+        };
+        descriptors.push_str(&format!("\n{descriptor}: {value};"));
+    }
+    let settings = axes
+        .iter()
+        .filter(|axis| axis.min != axis.max && !matches!(axis.tag.as_str(), "wght" | "wdth"))
+        .map(|axis| format!("\"{}\" {}", axis.tag, axis.min))
+        .collect::<Vec<_>>();
+    if !settings.is_empty() {
+        descriptors.push_str(&format!(
+            "\nfont-variation-settings: {};",
+            settings.join(", ")
+        ));
+    }
+    descriptors
+}
+
+/// Lists the source in `src` once per format, with the `format()` hint of that format
+/// in place of any given one.
+fn expand_src(src: &str, formats: &[FontFormat]) -> String {
+    let src = src.trim();
+    let source = src.strip_prefix("src:").unwrap_or(src).trim();
+    let source = source.strip_suffix(';').unwrap_or(source).trim_end();
+    let source = match source.split_once("format(") {
+        Some((head, tail)) => {
+            let tail = tail.split_once(')').map_or("", |(_, rest)| rest);
+            Cow::Owned(format!("{}{}", head.trim_end(), tail))
+        }
+        None => Cow::Borrowed(source),
+    };
+    let sources = formats
+        .iter()
+        .map(|format| {
+            format!(
+                "{} format(\"{}\")",
+                source.replace("<EXT>", format.ext()),
+                format.hint()
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("src: {};", sources.join(", "))
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FontFormat {
+    Woff2,
+    Woff,
+    Ttf,
+    Otf,
+}
+
+impl FontFormat {
+    pub fn ext(self) -> &'static str {
+        match self {
+            Self::Woff2 => "woff2",
+            Self::Woff => "woff",
+            Self::Ttf => "ttf",
+            Self::Otf => "otf",
+        }
+    }
+    /// Hint in the `format()` of CSS `src`.
+    pub fn hint(self) -> &'static str {
+        match self {
+            Self::Woff2 => "woff2",
+            Self::Woff => "woff",
+            Self::Ttf => "truetype",
+            Self::Otf => "opentype",
+        }
+    }
+}
+
+/// A variation axis pinned to `min` if equal to `max`, or limited to the range otherwise.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "String")]
+pub struct AxisPin {
+    pub tag: String,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl FromStr for AxisPin {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((tag, values)) = s.split_once('=') else {
+            bail!("expect <axis>=<value> or <axis>=<min>:<max>, got {:?}", s);
+        };
+        let tag = tag.trim();
+        if tag.len() != 4 || !tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            bail!("invalid axis tag: {:?}", tag);
+        }
+        let (min, max) = values.split_once(':').unwrap_or((values, values));
+        let (min, max) = (min.trim().parse::<f32>()?, max.trim().parse::<f32>()?);
+        if min.is_nan() || max.is_nan() || min > max {
+            bail!("empty range of axis {}: {}", tag, values);
+        }
+        Ok(Self {
+            tag: tag.to_owned(),
+            min,
+            max,
+        })
+    }
+}
+
+impl TryFrom<String> for AxisPin {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl UpdateInto for AxisPin {
+    fn update_into(&self, hasher: &mut dyn Hasher) {
+        hasher.update(self.tag.as_bytes());
+        hasher.update(&self.min.to_le_bytes());
+        hasher.update(&self.max.to_le_bytes());
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -32,6 +214,7 @@ pub struct BuilderConfig {
     pub js: JsBuilderConfig,
     #[serde(default)]
     pub font: FontBuilderConfig,
+    pub report: Option<ReportBuilderConfig>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -44,4 +227,112 @@ pub struct JsBuilderConfig {
 #[serde(deny_unknown_fields)]
 pub struct FontBuilderConfig {
     pub backend: Option<Con<Routine>>,
+    #[serde(default)]
+    #[cfg_attr(not(feature = "harfbuzz"), allow(dead_code))]
+    pub harfbuzz: HarfbuzzConfig,
+    #[serde(default)]
+    pub allsorts: AllsortsConfig,
+    pub command: Option<CommandConfig>,
+}
+
+/// External subsetter of the `command` backend.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    /// Name or path of the binary, e.g., `pyftsubset`.
+    pub program: PathBuf,
+    /// Arguments, where `{input}`, `{output}`, `{text_file}` and `{unicodes_file}`
+    /// are replaced by the paths of the font, the subset and the files listing the chunk's characters,
+    /// and `{face_index}` by the `face_index` of the font.
+    pub args: Vec<String>,
+    /// Seconds after which the command is killed.
+    pub timeout: Option<f64>,
+}
+
+/// Subset options of the `harfbuzz` backend.
+#[derive(Deserialize, Debug, Default)]
+#[cfg_attr(not(feature = "harfbuzz"), allow(dead_code))]
+#[serde(deny_unknown_fields)]
+pub struct HarfbuzzConfig {
+    /// Layout features to keep, e.g., `["kern", "palt"]`. Defaults to all of them.
+    pub keep_features: Option<Vec<String>>,
+    /// Layout features to drop, e.g., `["vert"]` for horizontal-only text.
+    #[serde(default)]
+    pub drop_features: Vec<String>,
+    /// Tables to drop, e.g., `["DSIG"]`.
+    #[serde(default)]
+    pub drop_tables: Vec<String>,
+    /// Removes the hinting instructions.
+    #[serde(default)]
+    pub no_hinting: bool,
+    /// Name IDs to keep in the `name` table. Defaults to HarfBuzz's, i.e., 0 to 6.
+    pub name_ids: Option<Vec<u16>>,
+    /// Keeps the glyph names of the `post` table.
+    #[serde(default)]
+    pub glyph_names: bool,
+    /// Keeps the glyph ids, emptying the removed glyphs instead of renumbering.
+    #[serde(default)]
+    pub retain_gids: bool,
+}
+
+/// Subset options of the `allsorts` backend.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AllsortsConfig {
+    /// Drops `GSUB`, `GPOS` and `GDEF`, which allsorts cannot subset, instead of failing.
+    #[serde(default)]
+    pub drop_layout: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ReportBuilderConfig {
+    pub json_path: Option<PathBuf>,
+    pub html_path: Option<PathBuf>,
+}
+
+#[test]
+fn test_axis_pin() {
+    let pin = |s: &str| s.parse::<AxisPin>().map(|a| (a.tag, a.min, a.max)).ok();
+    assert_eq!(pin("wght=400"), Some(("wght".into(), 400.0, 400.0)));
+    assert_eq!(pin("wght = 300:700"), Some(("wght".into(), 300.0, 700.0)));
+    assert_eq!(pin("wght=700:300"), None);
+    assert_eq!(pin("wg=400"), None);
+}
+
+#[test]
+fn test_axis_descriptors() {
+    let axes = |specs: &[&str]| specs.iter().map(|s| s.parse().unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        axis_descriptors(&axes(&["wght=400"])),
+        "\nfont-weight: 400;"
+    );
+    assert_eq!(
+        axis_descriptors(&axes(&["wdth=75:100", "wght=300:700", "GRAD=0"])),
+        "\nfont-weight: 300 700;\nfont-stretch: 75% 100%;"
+    );
+    assert_eq!(
+        axis_descriptors(&axes(&["wght=400", "opsz=12"])),
+        "\nfont-weight: 400;"
+    );
+    assert_eq!(
+        axis_descriptors(&axes(&["opsz=12:24", "wght=300:700", "GRAD=-50:0"])),
+        "\nfont-weight: 300 700;\nfont-variation-settings: \"opsz\" 12, \"GRAD\" -50;"
+    );
+}
+
+#[test]
+fn test_expand_src() {
+    let formats = [FontFormat::Woff2, FontFormat::Woff];
+    assert_eq!(
+        expand_src(r#"src: url("<ORIGIN>/XH_<FID>.<EXT>");"#, &formats),
+        r#"src: url("<ORIGIN>/XH_<FID>.woff2") format("woff2"), url("<ORIGIN>/XH_<FID>.woff") format("woff");"#
+    );
+    assert_eq!(
+        expand_src(
+            r#"src: url(<ORIGIN>/<FID>.<EXT>) format("woff2");"#,
+            &[FontFormat::Woff2, FontFormat::Ttf]
+        ),
+        r#"src: url(<ORIGIN>/<FID>.woff2) format("woff2"), url(<ORIGIN>/<FID>.ttf) format("truetype");"#
+    );
 }
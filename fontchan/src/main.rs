@@ -1,20 +1,33 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use clap::Parser;
-use config::Config;
-use fontchan_unicode::UEntry;
+use config::{Config, FontConfig, ReportBuilderConfig};
+use fontchan_partition::Algorithm;
+use fontchan_unicode::{UEntry, URange};
 use fontchan_util::{CloneS, WorkDir};
 use rayon::prelude::*;
-use serde::Deserialize;
 
 mod builder;
 mod config;
+mod per_page;
+mod simulate;
 
 #[derive(clap::Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
     #[clap(help = "The config TOML file")]
-    config_path: PathBuf,
+    config_path: Option<PathBuf>,
+    #[clap(long, help = "Build all chunks despite failures, then report them")]
+    keep_going: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    #[clap(about = "Simulate page loads to compare partition strategies")]
+    Simulate(simulate::Args),
 }
 
 fn main() -> Result<()> {
@@ -22,42 +35,220 @@ fn main() -> Result<()> {
 }
 
 fn run_main(cli: Cli) -> Result<()> {
-    let config_content = std::fs::read_to_string(&cli.config_path)?;
+    match cli.command {
+        Some(Command::Simulate(args)) => simulate::run(args),
+        None => run_build(
+            cli.config_path
+                .ok_or_else(|| anyhow!("config path required"))?,
+            cli.keep_going,
+        ),
+    }
+}
+
+fn load_config(config_path: &Path) -> Result<Config> {
+    let config_content = std::fs::read_to_string(config_path)?;
     let deserializer = toml::Deserializer::new(&config_content);
-    WorkDir::init_global(None, &cli.config_path, deserializer)?;
-    let config = Config::deserialize(toml::Deserializer::new(&config_content))?;
-
-    let partition = {
-        use fontchan_partition::{build_algorithm, Context};
-        let context = Context {
-            font_files: config.fonts.iter().map(|f| f.input_path.clone()).collect(),
-            ..Default::default()
-        };
-        let config = &config.partition;
-        build_algorithm(&context, &config)?.partition()
+    WorkDir::init_global(None, config_path, deserializer)?;
+    Config::parse(&config_content)
+}
+
+fn build_algorithm(config: &Config) -> Result<Algorithm> {
+    use fontchan_partition::{Context, FontFile};
+    let context = Context {
+        font_files: config
+            .fonts
+            .iter()
+            .map(|f| FontFile {
+                file: f.input_path.clone(),
+                face_index: f.face_index,
+            })
+            .collect(),
+        ..Default::default()
     };
+    fontchan_partition::build_algorithm(&context, &config.partition)
+}
 
-    let entries = partition
+fn make_entries(partition: &[URange]) -> Vec<UEntry<'_>> {
+    partition
         .iter()
         .enumerate()
         .map(|(i, range)| UEntry {
             name: i.to_string().into(),
             range,
         })
+        .collect()
+}
+
+fn run_build(config_path: PathBuf, keep_going: bool) -> Result<()> {
+    let config = load_config(&config_path)?;
+
+    let algorithm = build_algorithm(&config)?;
+    if config.fonts.iter().any(|f| f.family.is_some()) {
+        return run_build_per_font(&config, &algorithm, keep_going);
+    }
+    let (partition, routes) = match &config.per_page {
+        Some(per_page) => {
+            let Some(pages) = algorithm.pages() else {
+                bail!("per-page mode requires pages");
+            };
+            let plan = per_page.plan(&pages)?;
+            (plan.ranges, Some(plan.routes))
+        }
+        None => {
+            let partition = algorithm.partition()?;
+            if let Some(manifest_path) = &config.partition.manifest {
+                algorithm.to_manifest(&partition).save(manifest_path)?;
+            }
+            (partition, None)
+        }
+    };
+
+    let entries = make_entries(&partition);
+
+    let result = builder::FontBuilder::new(&config)?
+        .keep_going(keep_going)
+        .build(entries.par_iter().map(CloneS::clone_s))?;
+
+    let fragments = config
+        .fonts
+        .iter()
+        .map(FontConfig::css_fragments)
+        .collect::<Vec<_>>();
+    match &routes {
+        Some(routes) => builder::JSBuilder.build_pages(
+            config.builder.js.output_path.into(),
+            fragments.iter(),
+            routes,
+            &result,
+        )?,
+        None => builder::JSBuilder.build(
+            config.builder.js.output_path.into(),
+            fragments.iter(),
+            entries.iter().map(|e| e.range),
+            &result,
+        )?,
+    }
+
+    if let Some(report_config) = &config.builder.report {
+        builder::ReportBuilder.build(
+            report_config,
+            &config.fonts,
+            &entries,
+            &result,
+            algorithm.pages(),
+            algorithm.char_base()?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Builds with a partition for each font, by the text attributed to its family.
+///
+/// The manifest and the reports are written per font, with the index of the font
+/// inserted before the extension of their paths.
+fn run_build_per_font(config: &Config, algorithm: &Algorithm, keep_going: bool) -> Result<()> {
+    if config.per_page.is_some() {
+        bail!("per-page mode does not support per-font partitions");
+    }
+    let partitions = config
+        .fonts
+        .iter()
+        .map(|font| match &font.family {
+            Some(family) => algorithm.partition_for(family),
+            None => algorithm.partition(),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(manifest_path) = &config.partition.manifest {
+        for (idx, partition) in partitions.iter().enumerate() {
+            algorithm
+                .to_manifest(partition)
+                .save(per_font_path(manifest_path, idx))?;
+        }
+    }
+    let entries = partitions
+        .iter()
+        .map(|partition| make_entries(partition))
         .collect::<Vec<_>>();
 
-    let result =
-        builder::FontBuilder::new(&config)?.build(entries.par_iter().map(CloneS::clone_s))?;
+    let builder = builder::FontBuilder::new(config)?.keep_going(keep_going);
+    let results = entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entries)| builder.build_font(idx, entries.par_iter().map(CloneS::clone_s)));
+    let results = if keep_going {
+        builder::BuildFailures::merge(results)?
+    } else {
+        results.collect::<Result<Vec<_>>>()?
+    };
 
-    builder::JSBuilder.build(
-        config.builder.js.output_path.into(),
-        config.fonts.iter().map(|f| &f.css),
-        entries.iter().map(|e| e.range),
-        &result,
+    let fonts = partitions
+        .iter()
+        .map(Vec::as_slice)
+        .zip(&results)
+        .collect::<Vec<_>>();
+    let fragments = config
+        .fonts
+        .iter()
+        .map(FontConfig::css_fragments)
+        .collect::<Vec<_>>();
+    builder::JSBuilder.build_per_font(
+        config.builder.js.output_path.clone().into(),
+        fragments.iter(),
+        &fonts,
     )?;
+
+    if let Some(report_config) = &config.builder.report {
+        for (idx, font) in config.fonts.iter().enumerate() {
+            let report_config = ReportBuilderConfig {
+                json_path: report_config
+                    .json_path
+                    .as_ref()
+                    .map(|path| per_font_path(path, idx)),
+                html_path: report_config
+                    .html_path
+                    .as_ref()
+                    .map(|path| per_font_path(path, idx)),
+            };
+            let pages = match &font.family {
+                Some(family) => algorithm.pages_for(family),
+                None => algorithm.pages(),
+            };
+            builder::ReportBuilder.build(
+                &report_config,
+                std::slice::from_ref(font),
+                &entries[idx],
+                &results[idx],
+                pages,
+                algorithm.char_base()?,
+            )?;
+        }
+    }
     Ok(())
 }
 
+/// Inserts the index of a font before the extension of `path`, e.g., `report.0.html`.
+fn per_font_path(path: &Path, idx: usize) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_owned();
+    name.push(format!(".{}", idx));
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    path.with_file_name(name)
+}
+
+#[test]
+fn test_per_font_path() {
+    assert_eq!(
+        per_font_path(Path::new("out/report.html"), 1),
+        Path::new("out/report.1.html")
+    );
+    assert_eq!(
+        per_font_path(Path::new("manifest"), 0),
+        Path::new("manifest.0")
+    );
+}
+
 #[test]
 fn test() -> Result<()> {
     run_main(Cli::parse_from(vec![
@@ -0,0 +1,149 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use fontchan_partition::Page;
+use fontchan_unicode::{URange, URangeBuilder};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PerPageConfig {
+    /// Pages matching the same glob share one subset.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// The directory corresponding to the site root, stripped from page paths to form routes.
+    pub root: Option<PathBuf>,
+}
+
+pub struct PageSubsets {
+    pub ranges: Vec<URange>,
+    /// Route of each page and the index of the subset it uses.
+    pub routes: Vec<(String, usize)>,
+}
+
+struct Group<'a> {
+    chars: HashSet<char>,
+    paths: Vec<&'a Path>,
+}
+
+impl PerPageConfig {
+    fn route(&self, path: &Path) -> String {
+        let path = self
+            .root
+            .as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let mut route = String::new();
+        for component in path.components() {
+            if let std::path::Component::Normal(part) = component {
+                route.push('/');
+                route.push_str(&part.to_string_lossy());
+            }
+        }
+        route
+    }
+
+    pub fn plan(&self, pages: &[Page]) -> Result<PageSubsets> {
+        let patterns = self
+            .groups
+            .iter()
+            .map(|g| glob::Pattern::new(g))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let unrouted = pages.iter().filter(|page| page.path().is_none()).count();
+        if unrouted > 0 {
+            eprintln!(
+                "warning: {} pages without a path cannot be routed, their chars are left out",
+                unrouted
+            );
+        }
+        let mut pages = pages
+            .iter()
+            .filter_map(|page| Some((page.path()?, page)))
+            .collect::<Vec<_>>();
+        pages.sort_unstable_by_key(|(path, _)| *path);
+
+        let mut groups = Vec::<Group>::new();
+        let mut pattern_groups = vec![None; patterns.len()];
+        for (path, page) in pages {
+            let slot = patterns.iter().position(|p| p.matches_path(path));
+            let group_idx = match slot.and_then(|slot| pattern_groups[slot]) {
+                Some(group_idx) => group_idx,
+                None => {
+                    groups.push(Group {
+                        chars: HashSet::new(),
+                        paths: vec![],
+                    });
+                    if let Some(slot) = slot {
+                        pattern_groups[slot] = Some(groups.len() - 1);
+                    }
+                    groups.len() - 1
+                }
+            };
+            let group = &mut groups[group_idx];
+            group.chars.extend(page);
+            group.paths.push(path);
+        }
+
+        // Larger sets go first, so that a page whose chars are covered by
+        // an existing subset can reuse it.
+        groups.sort_by_key(|g| std::cmp::Reverse(g.chars.len()));
+        let mut subsets = Vec::<HashSet<char>>::new();
+        let mut routes = vec![];
+        for group in groups {
+            let idx = match subsets.iter().position(|s| s.is_superset(&group.chars)) {
+                Some(idx) => idx,
+                None => {
+                    subsets.push(group.chars);
+                    subsets.len() - 1
+                }
+            };
+            routes.extend(group.paths.into_iter().map(|p| (self.route(p), idx)));
+        }
+        routes.sort_unstable();
+
+        Ok(PageSubsets {
+            ranges: subsets
+                .into_iter()
+                .map(|s| URangeBuilder::from_chars(s.into_iter()).build())
+                .collect(),
+            routes,
+        })
+    }
+}
+
+#[test]
+fn test_plan() {
+    let page = |path: &str, chars: &str| chars.chars().collect::<Page>().with_path(path.into());
+    let pages = [
+        page("public/a/index.html", "abc"),
+        page("public/b.html", "ab"),
+        page("public/c.html", "xyz"),
+        page("public/posts/1.html", "p1"),
+        page("public/posts/2.html", "p2"),
+    ];
+    let config = PerPageConfig {
+        groups: vec!["public/posts/*".into()],
+        root: Some("public".into()),
+    };
+    let plan = config.plan(&pages).unwrap();
+    let subsets = plan
+        .ranges
+        .iter()
+        .map(|r| r.as_chars().collect::<String>())
+        .collect::<Vec<_>>();
+    assert_eq!(subsets, ["abc", "xyz", "p12"]);
+    assert_eq!(
+        plan.routes,
+        [
+            ("/a/index.html".to_owned(), 0),
+            ("/b.html".to_owned(), 0),
+            ("/c.html".to_owned(), 1),
+            ("/posts/1.html".to_owned(), 2),
+            ("/posts/2.html".to_owned(), 2),
+        ]
+    );
+}
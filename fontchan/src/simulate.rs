@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+use fontchan_partition::{Algorithm, Manifest, Page};
+use fontchan_unicode::URange;
+
+use crate::{build_algorithm, builder, config::Config, load_config, make_entries};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    #[clap(help = "The config TOML file providing the pages and fonts")]
+    config_path: PathBuf,
+    #[clap(
+        long,
+        help = "Simulate the partition in this manifest instead of the configured one"
+    )]
+    manifest: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "Another config TOML file or manifest to compare against. \
+                Relative paths in the config are resolved against the work dir of CONFIG_PATH"
+    )]
+    against: Option<PathBuf>,
+    #[clap(
+        long,
+        help = "A file of `<page path> <weight>` lines to weight the pages"
+    )]
+    weights: Option<PathBuf>,
+}
+
+struct Side {
+    label: String,
+    ranges: Vec<URange>,
+    sizes: Vec<u64>,
+    n_estimated: usize,
+}
+
+impl Side {
+    fn new(
+        label: String,
+        config: &Config,
+        algorithm: &Algorithm,
+        ranges: Vec<URange>,
+    ) -> Result<Self> {
+        let char_base_len = algorithm.char_base()?.map(|cb| cb.len());
+        let bytes_per_char =
+            builder::estimate_bytes_per_char(&config.fonts, char_base_len, &ranges)?;
+        let built = builder::FontBuilder::new(config)?.built_sizes(&make_entries(&ranges));
+        let n_estimated = built.iter().filter(|size| size.is_none()).count();
+        let sizes = ranges
+            .iter()
+            .zip(built)
+            .map(|(range, size)| {
+                size.unwrap_or_else(|| (range.as_chars().count() as f64 * bytes_per_char) as u64)
+            })
+            .collect();
+        Ok(Self {
+            label,
+            ranges,
+            sizes,
+            n_estimated,
+        })
+    }
+
+    fn simulate(&self, pages: &[Page], weights: &HashMap<PathBuf, f64>) -> Outcome {
+        let owners = self
+            .ranges
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, range)| range.as_chars().map(move |ch| (ch, idx)))
+            .collect::<HashMap<_, _>>();
+        let mut requests = Vec::with_capacity(pages.len());
+        let mut bytes = Vec::with_capacity(pages.len());
+        for page in pages {
+            let weight = page
+                .path()
+                .and_then(|p| weights.get(p))
+                .copied()
+                .unwrap_or(page.weight() as f64);
+            let needed = page
+                .into_iter()
+                .filter_map(|ch| owners.get(ch).copied())
+                .collect::<HashSet<_>>();
+            let n_bytes = needed.iter().map(|idx| self.sizes[*idx]).sum::<u64>();
+            requests.push((needed.len() as f64, weight));
+            bytes.push((n_bytes as f64, weight));
+        }
+        Outcome {
+            requests: Summary::new(requests),
+            bytes: Summary::new(bytes),
+        }
+    }
+}
+
+struct Outcome {
+    requests: Summary,
+    bytes: Summary,
+}
+
+struct Summary {
+    mean: f64,
+    p50: f64,
+    p90: f64,
+    max: f64,
+}
+
+impl Summary {
+    fn new(mut samples: Vec<(f64, f64)>) -> Self {
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total = samples.iter().map(|(_, w)| w).sum::<f64>();
+        let mean = samples.iter().map(|(v, w)| v * w).sum::<f64>() / total.max(f64::MIN_POSITIVE);
+        let quantile = |q: f64| {
+            let mut acc = 0.0;
+            for (v, w) in &samples {
+                acc += w;
+                if acc >= q * total {
+                    return *v;
+                }
+            }
+            samples.last().map_or(0.0, |(v, _)| *v)
+        };
+        Self {
+            mean,
+            p50: quantile(0.5),
+            p90: quantile(0.9),
+            max: samples.last().map_or(0.0, |(v, _)| *v),
+        }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "mean {:.2}, p50 {}, p90 {}, max {}",
+            self.mean, self.p50, self.p90, self.max
+        )
+    }
+}
+
+fn load_weights(path: &Path) -> Result<HashMap<PathBuf, f64>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("cannot read weights {}: {}", path.display(), e))?;
+    let mut weights = HashMap::new();
+    for (lineno, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((page, weight)) = line.rsplit_once(char::is_whitespace) else {
+            bail!(
+                "{}:{}: expect `<page path> <weight>`",
+                path.display(),
+                lineno + 1
+            );
+        };
+        let weight = weight
+            .parse::<f64>()
+            .map_err(|e| anyhow!("{}:{}: {}", path.display(), lineno + 1, e))?;
+        weights.insert(PathBuf::from(page.trim()), weight);
+    }
+    Ok(weights)
+}
+
+pub fn run(args: Args) -> Result<()> {
+    // Paths on the command line are relative to the current directory,
+    // which will be changed to the work dir when loading the config.
+    let manifest = args.manifest.map(std::path::absolute).transpose()?;
+    let against = args.against.map(std::path::absolute).transpose()?;
+    let weights = args.weights.map(std::path::absolute).transpose()?;
+
+    let config = load_config(&args.config_path)?;
+    let algorithm = build_algorithm(&config)?;
+    let Some(pages) = algorithm.pages() else {
+        bail!("no pages configured for simulation");
+    };
+    let weights = weights
+        .as_deref()
+        .map(load_weights)
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut sides = vec![];
+    let ranges = match &manifest {
+        Some(path) => Manifest::load(path)?.ranges,
+        None => algorithm.partition()?,
+    };
+    let label = manifest.as_ref().unwrap_or(&args.config_path);
+    let label = label.display().to_string();
+    sides.push(Side::new(label, &config, &algorithm, ranges)?);
+    if let Some(path) = &against {
+        let label = path.display().to_string();
+        let side = if path.extension().is_some_and(|ext| ext == "toml") {
+            let other = Config::load(path)?;
+            let other_algorithm = build_algorithm(&other)?;
+            let ranges = other_algorithm.partition()?;
+            Side::new(label, &other, &other_algorithm, ranges)?
+        } else {
+            let ranges = Manifest::load(path)?.ranges;
+            Side::new(label, &config, &algorithm, ranges)?
+        };
+        sides.push(side);
+    }
+
+    let outcomes = sides
+        .iter()
+        .map(|side| side.simulate(&pages, &weights))
+        .collect::<Vec<_>>();
+    println!("{} pages simulated", pages.len());
+    for (name, (side, outcome)) in ["A", "B"].iter().zip(sides.iter().zip(&outcomes)) {
+        println!(
+            "[{}] {} ({} chunks, {} sizes estimated)",
+            name,
+            side.label,
+            side.ranges.len(),
+            side.n_estimated
+        );
+        println!("  requests per page: {}", outcome.requests);
+        println!("  bytes per page:    {}", outcome.bytes);
+    }
+    if let [a, b] = outcomes.as_slice() {
+        let change = |a: f64, b: f64| (b - a) / a.max(f64::MIN_POSITIVE) * 100.0;
+        println!(
+            "B vs A: requests mean {:+.1}%, bytes mean {:+.1}%",
+            change(a.requests.mean, b.requests.mean),
+            change(a.bytes.mean, b.bytes.mean)
+        );
+    }
+    Ok(())
+}